@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+
+use super::grammar::types::*;
+use super::grammar::position_at;
+use super::grammar::normal_form::{chomsky_normal_form, NormalForm};
+
+/// The ways a nonterminal derives some tokens: a production of the Chomsky
+/// normal form, and where the second symbol starts, for `A -> B C`
+type Entry = BTreeMap<Nonterm, Vec<(usize, usize)>>;
+
+/// A CYK parser, working on the Chomsky normal form of any context free
+/// grammar in O(n³) time. Its trees are given back in the original grammar.
+pub struct CYKParser {
+    normal: NormalForm,
+}
+
+impl CYKParser {
+    /// Builds the parser of `grammar`, adding the names of the nonterminals
+    /// the normal form makes up to `names`
+    pub fn new(grammar: &Grammar, names: &mut SymbolNames) -> CYKParser {
+        CYKParser { normal: chomsky_normal_form(grammar, names) }
+    }
+
+    pub fn normal_form(&self) -> &NormalForm {
+        &self.normal
+    }
+
+    /// The triangular table: the entry at `[start][len - 1]` tells which
+    /// nonterminals derive the `len` tokens from `start`, and how
+    fn table<T: Token>(&self, input: &[T]) -> Vec<Vec<Entry>> {
+        let productions = &self.normal.grammar.productions;
+        let mut table: Vec<Vec<Entry>> = (0..input.len()).map(|start| vec![Entry::new(); input.len() - start]).collect();
+        for (start, token) in input.iter().enumerate() {
+            for (index, prod) in productions.iter().enumerate() {
+                if prod.b == [Symbol::Term(token.term())] {
+                    table[start][0].entry(prod.s).or_default().push((index, start + 1));
+                }
+            }
+        }
+        for len in 2..=input.len() {
+            for start in 0..=input.len() - len {
+                let mut entry = Entry::new();
+                for split in 1..len {
+                    let (left, right) = (&table[start][split - 1], &table[start + split][len - split - 1]);
+                    for (index, prod) in productions.iter().enumerate() {
+                        if let [Symbol::Nonterm(b), Symbol::Nonterm(c)] = prod.b.as_slice() {
+                            if left.contains_key(b) && right.contains_key(c) {
+                                entry.entry(prod.s).or_default().push((index, start + split));
+                            }
+                        }
+                    }
+                }
+                table[start][len - 1] = entry;
+            }
+        }
+        table
+    }
+
+    /// The parses of `input` in the normal form, if it has some
+    fn forest<T: Token>(&self, input: &[T]) -> Option<ParseForest> {
+        let init_nonterm = self.normal.grammar.init_nonterm;
+        let table = self.table(input);
+        if !table.first()?.last()?.contains_key(&init_nonterm) {
+            return None;
+        }
+        let mut builder = ForestBuilder { parser: self, table: &table, input, nodes: Vec::new(), index: BTreeMap::new() };
+        let root = builder.node(Symbol::Nonterm(init_nonterm), 0, input.len());
+        Some(ParseForest { nodes: builder.nodes, root })
+    }
+
+    /// Whether `input` is a sentence of the grammar
+    pub fn recognize<T: Token>(&self, input: &[T]) -> bool {
+        match input.is_empty() {
+            true => self.normal.accepts_empty,
+            false => self.table(input)[0].last().is_some_and(|entry| entry.contains_key(&self.normal.grammar.init_nonterm)),
+        }
+    }
+
+    /// One parse tree of `input` if it is a sentence, in the original grammar
+    pub fn parse_tree<T: Token>(&self, input: &[T]) -> Option<ParseTree> {
+        match input.is_empty() {
+            true => self.normal.empty_tree(),
+            false => self.forest(input).map(|forest| self.normal.translate(&forest.tree())),
+        }
+    }
+
+    /// Every parse tree of `input` in the original grammar, but for the ways
+    /// of deriving ε, where one is taken
+    pub fn parse_trees<T: Token>(&self, input: &[T]) -> Vec<ParseTree> {
+        match input.is_empty() {
+            true => self.normal.empty_tree().into_iter().collect(),
+            false => self.forest(input)
+                .map(|forest| forest.trees().iter().flat_map(|tree| self.normal.translate_all(tree)).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The rightmost derivation of `input` like `LRGrammar::parse` gives, if
+    /// it is a sentence
+    pub fn parse<T: Token>(&self, input: &[T]) -> Option<Vec<Vec<Symbol>>> {
+        self.parse_tree(input).map(|tree| tree.rightmost_derivation())
+    }
+}
+
+/// Reads the derivations of the table back into a forest
+struct ForestBuilder<'a, T> {
+    parser: &'a CYKParser,
+    table: &'a [Vec<Entry>],
+    input: &'a [T],
+    nodes: Vec<ForestNode>,
+    index: BTreeMap<(Symbol, usize, usize), usize>,
+}
+
+impl<'a, T: Token> ForestBuilder<'a, T> {
+    fn node(&mut self, symbol: Symbol, start: usize, end: usize) -> usize {
+        if let Some(&node) = self.index.get(&(symbol, start, end)) {
+            return node;
+        }
+        let node = self.nodes.len();
+        self.nodes.push(ForestNode {
+            symbol,
+            start,
+            end,
+            position: position_at(self.input, start),
+            alternatives: Vec::new(),
+        });
+        self.index.insert((symbol, start, end), node);
+        if let Symbol::Nonterm(nonterm) = symbol {
+            let ways = self.table[start][end - start - 1][&nonterm].clone();
+            for (index, split) in ways {
+                let prod = self.parser.normal.grammar.productions[index].clone();
+                let children = match prod.b.as_slice() {
+                    [b, c] => vec![self.node(*b, start, split), self.node(*c, split, end)],
+                    [a] => vec![self.node(*a, start, end)],
+                    _ => unreachable!("Productions in Chomsky normal form"),
+                };
+                self.nodes[node].alternatives.push((prod, children));
+            }
+        }
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::reader::{read_grammar, tokenize};
+    use crate::earley::EarleyParser;
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::lalr_state::LALRState;
+
+    fn cyk(grammar: &str) -> (CYKParser, Grammar, GrammarSpec) {
+        let mut spec = read_grammar(grammar).unwrap();
+        let grammar = Grammar::build(1, spec.productions.clone());
+        (CYKParser::new(&grammar, &mut spec.names), grammar, spec)
+    }
+
+    #[test]
+    fn same_as_lr() {
+        let (parser, _, spec) = cyk(r#"
+            E -> E + T | T
+            T -> T * F | F
+            F -> "(" E ")" | n
+        "#);
+        let lr: LRGrammar<LALRState> = LRGrammar::build(1, spec.productions.clone()).unwrap();
+        // Every string of up to 5 tokens, then pseudo random longer ones
+        let alphabet: Vec<char> = "n+*()".chars().collect();
+        let mut inputs = vec![String::new()];
+        for len in 1..=5 {
+            for mut code in 0..alphabet.len().pow(len) {
+                let mut input = String::new();
+                for _ in 0..len {
+                    input.push(alphabet[code % alphabet.len()]);
+                    code /= alphabet.len();
+                }
+                inputs.push(input);
+            }
+        }
+        let mut seed: u64 = 12345;
+        for _ in 0..200 {
+            let mut input = String::new();
+            for _ in 0..12 {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                input.push(alphabet[(seed >> 33) as usize % alphabet.len()]);
+            }
+            inputs.push(input);
+        }
+        let mut sentences = 0;
+        for input in &inputs {
+            let input = tokenize(input, &spec.names).unwrap();
+            assert_eq!(parser.parse(&input), lr.parse(&input).ok());
+            assert_eq!(parser.recognize(&input), lr.parse(&input).is_ok());
+            sentences += parser.recognize(&input) as usize;
+        }
+        assert!(sentences > 10);
+    }
+
+    #[test]
+    fn all_trees() {
+        let (parser, grammar, spec) = cyk("E -> E + E | E * E | n");
+        let earley = EarleyParser::new(&grammar);
+        let input = tokenize("n+n*n+n", &spec.names).unwrap();
+        let mut trees = parser.parse_trees(&input);
+        let mut expected = earley.parse_forest(&input).unwrap().trees();
+        assert_eq!(trees.len(), 5);
+        trees.sort_by_key(|tree| format!("{:?}", tree));
+        expected.sort_by_key(|tree| format!("{:?}", tree));
+        assert_eq!(trees, expected);
+        assert_eq!(parser.parse_trees(&tokenize("n+", &spec.names).unwrap()), vec![]);
+
+        // Both unit chains make S -> a in the normal form
+        let (parser, grammar, spec) = cyk("
+            S -> A | B
+            A -> a
+            B -> a
+        ");
+        let earley = EarleyParser::new(&grammar);
+        let input = tokenize("a", &spec.names).unwrap();
+        let mut trees = parser.parse_trees(&input);
+        let mut expected = earley.parse_forest(&input).unwrap().trees();
+        assert_eq!(trees.len(), 2);
+        trees.sort_by_key(|tree| format!("{:?}", tree));
+        expected.sort_by_key(|tree| format!("{:?}", tree));
+        assert_eq!(trees, expected);
+    }
+
+    #[test]
+    fn empty_productions() {
+        let (parser, grammar, spec) = cyk("
+            S -> a S b | A
+            A -> ε | c
+        ");
+        let earley = EarleyParser::new(&grammar);
+        for input in &["", "c", "ab", "aacbb", "aab", "ca"] {
+            let input = tokenize(input, &spec.names).unwrap();
+            assert_eq!(parser.parse_tree(&input), earley.parse_tree(&input).ok());
+        }
+    }
+}
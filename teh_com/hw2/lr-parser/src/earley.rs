@@ -0,0 +1,324 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::grammar::types::*;
+use super::grammar::position_at;
+use super::lr_grammar::types::ParseError;
+
+/// A dotted production, with the index of the token it started at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct EarleyItem {
+    /// Index in `Grammar::productions`
+    prod: usize,
+    dot: usize,
+    origin: usize,
+}
+
+/// The items of each set, in the order they were added
+struct Chart {
+    sets: Vec<Vec<EarleyItem>>,
+    members: Vec<BTreeSet<EarleyItem>>,
+}
+
+impl Chart {
+    fn add(&mut self, set: usize, item: EarleyItem) {
+        if self.members[set].insert(item) {
+            self.sets[set].push(item);
+        }
+    }
+
+    fn contains(&self, set: usize, item: EarleyItem) -> bool {
+        self.members[set].contains(&item)
+    }
+}
+
+/// An Earley parser, working on any context free grammar: left recursive,
+/// ambiguous, or with conflicts in every LR automaton. It is slower than an
+/// LR parser, but a good reference to check them against.
+pub struct EarleyParser<'a> {
+    grammar: &'a Grammar,
+    prods_of: BTreeMap<Nonterm, Vec<usize>>,
+}
+
+impl<'a> EarleyParser<'a> {
+    pub fn new(grammar: &'a Grammar) -> EarleyParser<'a> {
+        let mut prods_of: BTreeMap<Nonterm, Vec<usize>> = BTreeMap::new();
+        for (index, prod) in grammar.productions.iter().enumerate() {
+            prods_of.entry(prod.s).or_default().push(index);
+        }
+        EarleyParser { grammar, prods_of }
+    }
+
+    fn next_symbol(&self, item: EarleyItem) -> Option<Symbol> {
+        self.grammar.productions[item.prod].b.get(item.dot).cloned()
+    }
+
+    fn prods_of(&self, nonterm: Nonterm) -> &[usize] {
+        self.prods_of.get(&nonterm).map_or(&[], |prods| prods)
+    }
+
+    fn is_accepting(&self, item: EarleyItem) -> bool {
+        let prod = &self.grammar.productions[item.prod];
+        item.origin == 0 && item.dot == prod.b.len() && prod.s == self.grammar.init_nonterm
+    }
+
+    /// Fills the sets of items up to the end of the input, or up to the
+    /// last one before a token no item can scan
+    fn chart<T: Token>(&self, input: &[T]) -> Chart {
+        let mut chart = Chart { sets: vec![Vec::new()], members: vec![BTreeSet::new()] };
+        for &prod in self.prods_of(self.grammar.init_nonterm) {
+            chart.add(0, EarleyItem { prod, dot: 0, origin: 0 });
+        }
+        for index in 0..=input.len() {
+            if index < input.len() {
+                chart.sets.push(Vec::new());
+                chart.members.push(BTreeSet::new());
+            }
+            let mut next = 0;
+            while let Some(&item) = chart.sets[index].get(next) {
+                next += 1;
+                match self.next_symbol(item) {
+                    None => {
+                        let nonterm = self.grammar.productions[item.prod].s;
+                        let waiting: Vec<_> = chart.sets[item.origin]
+                            .iter()
+                            .filter(|&&other| self.next_symbol(other) == Some(Symbol::Nonterm(nonterm)))
+                            .cloned()
+                            .collect();
+                        for other in waiting {
+                            chart.add(index, EarleyItem { dot: other.dot + 1, ..other });
+                        }
+                    }
+                    Some(Symbol::Nonterm(nonterm)) => {
+                        for &prod in self.prods_of(nonterm) {
+                            chart.add(index, EarleyItem { prod, dot: 0, origin: index });
+                        }
+                        // Completing an empty nonterminal would not see the
+                        // items predicting it afterwards, so skip it now
+                        if self.grammar.nullable_list.contains(&nonterm) {
+                            chart.add(index, EarleyItem { dot: item.dot + 1, ..item });
+                        }
+                    }
+                    Some(Symbol::Term(term)) => {
+                        if input.get(index).map(|token| token.term()) == Some(term) {
+                            chart.add(index + 1, EarleyItem { dot: item.dot + 1, ..item });
+                        }
+                    }
+                }
+            }
+            if index < input.len() && chart.sets[index + 1].is_empty() {
+                chart.sets.pop();
+                chart.members.pop();
+                break;
+            }
+        }
+        chart
+    }
+
+    /// Whether `input` is a sentence of the grammar
+    pub fn recognize<T: Token>(&self, input: &[T]) -> bool {
+        let chart = self.chart(input);
+        chart.sets.len() == input.len() + 1 && chart.sets[input.len()].iter().any(|&item| self.is_accepting(item))
+    }
+
+    /// Every parse of `input`, or where it stops being a prefix of a sentence
+    pub fn parse_forest<T: Token>(&self, input: &[T]) -> Result<ParseForest, ParseError> {
+        let chart = self.chart(input);
+        let last = chart.sets.len() - 1;
+        if last < input.len() || !chart.sets[last].iter().any(|&item| self.is_accepting(item)) {
+            let mut expected = BTreeSet::new();
+            for &item in &chart.sets[last] {
+                match self.next_symbol(item) {
+                    Some(Symbol::Term(term)) if term != ERROR_TERM => {
+                        expected.insert(term);
+                    }
+                    None if self.is_accepting(item) => {
+                        expected.insert(END_TERM);
+                    }
+                    _ => {}
+                }
+            }
+            return Err(ParseError {
+                position: position_at(input, last),
+                unexpected: input.get(last).map(|token| token.term()),
+                expected: expected.into_iter().collect(),
+            });
+        }
+        let mut builder = ForestBuilder { parser: self, chart: &chart, input, nodes: Vec::new(), index: BTreeMap::new() };
+        let root = builder.node(Symbol::Nonterm(self.grammar.init_nonterm), 0, input.len());
+        Ok(ParseForest { nodes: builder.nodes, root })
+    }
+
+    /// Parses `input` into a concrete syntax tree, one of them if the grammar
+    /// is ambiguous
+    pub fn parse_tree<T: Token>(&self, input: &[T]) -> Result<ParseTree, ParseError> {
+        self.parse_forest(input).map(|forest| forest.tree())
+    }
+
+    /// Parses `input`, returning its rightmost derivation from the start
+    /// symbol like `LRGrammar::parse` does
+    pub fn parse<T: Token>(&self, input: &[T]) -> Result<Vec<Vec<Symbol>>, ParseError> {
+        self.parse_tree(input).map(|tree| tree.rightmost_derivation())
+    }
+}
+
+/// Reads the derivations back from the completed items of the chart
+struct ForestBuilder<'a, 'b, T> {
+    parser: &'b EarleyParser<'a>,
+    chart: &'b Chart,
+    input: &'b [T],
+    nodes: Vec<ForestNode>,
+    index: BTreeMap<(Symbol, usize, usize), usize>,
+}
+
+impl<'a, 'b, T: Token> ForestBuilder<'a, 'b, T> {
+    /// The node of `symbol` deriving the tokens from `start` to `end`, which
+    /// it must derive
+    fn node(&mut self, symbol: Symbol, start: usize, end: usize) -> usize {
+        if let Some(&node) = self.index.get(&(symbol, start, end)) {
+            return node;
+        }
+        let node = self.nodes.len();
+        self.nodes.push(ForestNode {
+            symbol,
+            start,
+            end,
+            position: position_at(self.input, start),
+            alternatives: Vec::new(),
+        });
+        self.index.insert((symbol, start, end), node);
+        if let Symbol::Nonterm(nonterm) = symbol {
+            for &prod in self.parser.prods_of(nonterm) {
+                let len = self.parser.grammar.productions[prod].b.len();
+                if !self.chart.contains(end, EarleyItem { prod, dot: len, origin: start }) {
+                    continue;
+                }
+                for children in self.children(prod, len, start, end) {
+                    let prod = self.parser.grammar.productions[prod].clone();
+                    self.nodes[node].alternatives.push((prod, children));
+                }
+            }
+        }
+        node
+    }
+
+    /// The nodes the symbols before `dot` can derive, if the item with `dot`
+    /// started at `start` gets to `end`
+    fn children(&mut self, prod: usize, dot: usize, start: usize, end: usize) -> Vec<Vec<usize>> {
+        if dot == 0 {
+            return if start == end { vec![vec![]] } else { vec![] };
+        }
+        let before = EarleyItem { prod, dot: dot - 1, origin: start };
+        let mut result = Vec::new();
+        match self.parser.grammar.productions[prod].b[dot - 1] {
+            Symbol::Term(term) => {
+                let scanned = end > start && self.input[end - 1].term() == term;
+                if scanned && self.chart.contains(end - 1, before) {
+                    let leaf = self.node(Symbol::Term(term), end - 1, end);
+                    for mut children in self.children(prod, dot - 1, start, end - 1) {
+                        children.push(leaf);
+                        result.push(children);
+                    }
+                }
+            }
+            Symbol::Nonterm(nonterm) => {
+                for middle in start..=end {
+                    if !self.chart.contains(middle, before) || !self.completes(nonterm, middle, end) {
+                        continue;
+                    }
+                    let child = self.node(Symbol::Nonterm(nonterm), middle, end);
+                    for mut children in self.children(prod, dot - 1, start, middle) {
+                        children.push(child);
+                        result.push(children);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Whether `nonterm` derives the tokens from `start` to `end`
+    fn completes(&self, nonterm: Nonterm, start: usize, end: usize) -> bool {
+        self.parser.prods_of(nonterm).iter().any(|&prod| {
+            let len = self.parser.grammar.productions[prod].b.len();
+            self.chart.contains(end, EarleyItem { prod, dot: len, origin: start })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::reader::{read_grammar, tokenize};
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::canonical_lr_state::CanonicalLRState;
+
+    fn grammar(text: &str) -> (Grammar, SymbolNames) {
+        let GrammarSpec { productions, names, .. } = read_grammar(text).unwrap();
+        (Grammar::build(1, productions), names)
+    }
+
+    #[test]
+    fn same_as_lr() {
+        let GrammarSpec { productions, names, .. } = read_grammar(r#"
+            E -> E + T | T
+            T -> T * F | F
+            F -> "(" E ")" | n
+        "#).unwrap();
+        let lr: LRGrammar<CanonicalLRState> = LRGrammar::build(1, productions.clone()).unwrap();
+        let grammar = Grammar::build(1, productions);
+        let earley = EarleyParser::new(&grammar);
+        for input in &["n", "n+n*n", "(n+n)*n", "((n))", "n+", "n)", "(n+n", "", "n n", "*n"] {
+            let input = tokenize(input, &names).unwrap();
+            assert_eq!(earley.parse(&input), lr.parse(&input));
+            assert_eq!(earley.recognize(&input), lr.parse(&input).is_ok());
+        }
+    }
+
+    #[test]
+    fn general_grammars() {
+        // Not LR(k) for any k
+        let (palindromes, names) = grammar("S -> a S a | b S b | a | b | ε");
+        let parser = EarleyParser::new(&palindromes);
+        let recognize = |input| parser.recognize(&tokenize(input, &names).unwrap());
+        assert!(recognize(""));
+        assert!(recognize("abba"));
+        assert!(recognize("abaaba"));
+        assert!(recognize("ababa"));
+        assert!(!recognize("ab"));
+        assert!(!recognize("abbb"));
+
+        let (ambiguous, names) = grammar("E -> E + E | n");
+        let parser = EarleyParser::new(&ambiguous);
+        let forest = parser.parse_forest(&tokenize("n+n+n+n", &names).unwrap()).unwrap();
+        assert_eq!(forest.count(), Some(5));
+        assert_eq!(forest.trees().len(), 5);
+        let tree = parser.parse_tree(&tokenize("n+n", &names).unwrap()).unwrap();
+        assert_eq!(tree.leaves().len(), 3);
+    }
+
+    #[test]
+    fn empty_productions_and_cycles() {
+        let (grammar, names) = grammar("
+            S -> A S a | a
+            A -> ε
+        ");
+        let parser = EarleyParser::new(&grammar);
+        let forest = parser.parse_forest(&tokenize("aaa", &names).unwrap()).unwrap();
+        assert_eq!(forest.count(), Some(1));
+        assert_eq!(
+            names.symbols(&forest.tree().rightmost_derivation()[2]),
+            "A A S a a",
+        );
+
+        let (grammar, names) = self::grammar("
+            S -> S | A a
+            A -> ε | A
+        ");
+        let parser = EarleyParser::new(&grammar);
+        let forest = parser.parse_forest(&tokenize("a", &names).unwrap()).unwrap();
+        assert_eq!(forest.count(), None);
+        assert_eq!(parser.parse(&tokenize("a", &names).unwrap()).unwrap().last().unwrap(), &vec![
+            Symbol::Term(names.find_term("a").unwrap()),
+        ]);
+    }
+}
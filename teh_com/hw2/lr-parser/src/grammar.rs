@@ -1,4 +1,8 @@
 pub mod types;
+pub mod reader;
+pub mod lint;
+pub mod transform;
+pub mod normal_form;
 
 use std::collections::{BTreeSet, BTreeMap};
 
@@ -32,8 +36,8 @@ impl Grammar {
             for prod in &productions {
                 let curr_nullable = prod.b.iter().filter(|sym| {
                     match sym {
-                        &&Symbol::Term(..) => true,
-                        &&Symbol::Nonterm(t) => !nullable_list.contains(&t),
+                        Symbol::Term(..) => true,
+                        Symbol::Nonterm(t) => !nullable_list.contains(t),
                     }
                 }).count() == 0;
                 if curr_nullable {
@@ -74,7 +78,6 @@ impl Grammar {
                         }
                     }
                 }
-                drop(current_first);
                 {
                     let first = first_map.get_mut(&prod.s).expect("Shoul be initialized properly");
                     std::mem::swap(first, &mut empty_set);
@@ -139,6 +142,335 @@ impl Grammar {
     }
 }
 
+impl Default for SymbolNames {
+    fn default() -> SymbolNames {
+        SymbolNames {
+            nonterms: Vec::new(),
+            terms: vec!["$".to_string(), "error".to_string()],
+        }
+    }
+}
+
+impl SymbolNames {
+    pub fn find_term(&self, name: &str) -> Option<Term> {
+        self.terms.iter().position(|term| term == name)
+    }
+
+    pub fn find_nonterm(&self, name: &str) -> Option<Nonterm> {
+        self.nonterms.iter().position(|nonterm| nonterm == name)
+    }
+
+    /// The terminal called `name`, added if it is new
+    pub fn add_term(&mut self, name: &str) -> Term {
+        self.find_term(name).unwrap_or_else(|| {
+            self.terms.push(name.to_string());
+            self.terms.len() - 1
+        })
+    }
+
+    /// The nonterminal called `name`, added if it is new
+    pub fn add_nonterm(&mut self, name: &str) -> Nonterm {
+        self.find_nonterm(name).unwrap_or_else(|| {
+            self.nonterms.push(name.to_string());
+            self.nonterms.len() - 1
+        })
+    }
+
+    /// A new nonterminal called `like`, with quotes added until the name is
+    /// not taken
+    pub fn add_fresh_nonterm(&mut self, like: &str) -> Nonterm {
+        let mut name = like.to_string();
+        while self.find_nonterm(&name).is_some() {
+            name.push('\'');
+        }
+        self.add_nonterm(&name)
+    }
+
+    pub fn symbol(&self, sym: Symbol) -> String {
+        match sym {
+            Symbol::Nonterm(t) => self.nonterms
+                .get(t)
+                .cloned()
+                .unwrap_or_else(|| format!("<{}>", t)),
+            Symbol::Term(t) => self.terms
+                .get(t)
+                .cloned()
+                .unwrap_or_else(|| format!("<t{}>", t)),
+        }
+    }
+
+    pub fn symbols(&self, syms: &[Symbol]) -> String {
+        syms.iter().map(|&sym| self.symbol(sym)).collect::<Vec<_>>().join(" ")
+    }
+
+    pub fn production(&self, prod: &Production) -> String {
+        if prod.b.is_empty() {
+            format!("{} -> ε", self.symbol(Symbol::Nonterm(prod.s)))
+        } else {
+            format!("{} -> {}", self.symbol(Symbol::Nonterm(prod.s)), self.symbols(&prod.b))
+        }
+    }
+}
+
+impl Position {
+    pub fn of_offset(input: &[u8], offset: usize) -> Position {
+        let before = &input[..offset.min(input.len())];
+        let line_start = before.iter().rposition(|&c| c == b'\n').map_or(0, |pos| pos + 1);
+        Position {
+            offset,
+            line: before.iter().filter(|&&c| c == b'\n').count() + 1,
+            column: offset - line_start + 1,
+        }
+    }
+
+    /// The position just past `text`, read from here
+    pub fn advance(self, text: &[u8]) -> Position {
+        let mut result = self;
+        for &c in text {
+            result.offset += 1;
+            if c == b'\n' {
+                result.line += 1;
+                result.column = 1;
+            } else {
+                result.column += 1;
+            }
+        }
+        result
+    }
+}
+
+impl<V> Token for Lexeme<V> {
+    fn term(&self) -> Term {
+        self.term
+    }
+
+    fn start(&self) -> Position {
+        self.start
+    }
+
+    fn end(&self) -> Position {
+        self.end
+    }
+}
+
+/// Where the token at `index` starts, or where the input ends
+pub fn position_at<T: Token>(input: &[T], index: usize) -> Position {
+    match (input.get(index), input.last()) {
+        (Some(token), _) => token.start(),
+        (None, Some(last)) => last.end(),
+        (None, None) => Position { offset: 0, line: 1, column: 1 },
+    }
+}
+
+impl ParseTree {
+    pub fn symbol(&self) -> Symbol {
+        match self {
+            ParseTree::Node { nonterm, .. } => Symbol::Nonterm(*nonterm),
+            ParseTree::Leaf { term, .. } => Symbol::Term(*term),
+        }
+    }
+
+    /// The terminals at the leaves, from left to right
+    pub fn leaves(&self) -> Vec<Term> {
+        match self {
+            ParseTree::Node { children, .. } => children.iter().flat_map(|child| child.leaves()).collect(),
+            ParseTree::Leaf { term, .. } => vec![*term],
+        }
+    }
+
+    /// The sentential forms of the rightmost derivation of the tree, from the
+    /// symbol at its root down to its leaves
+    pub fn rightmost_derivation(&self) -> Vec<Vec<Symbol>> {
+        let mut form = vec![self];
+        let mut result = vec![vec![self.symbol()]];
+        while let Some(pos) = form.iter().rposition(|tree| matches!(tree, ParseTree::Node { .. })) {
+            if let ParseTree::Node { children, .. } = form[pos] {
+                form.splice(pos..=pos, children);
+            }
+            result.push(form.iter().map(|tree| tree.symbol()).collect());
+        }
+        result
+    }
+
+    /// The sentential forms of the leftmost derivation of the tree
+    pub fn leftmost_derivation(&self) -> Vec<Vec<Symbol>> {
+        let mut form = vec![self];
+        let mut result = vec![vec![self.symbol()]];
+        while let Some(pos) = form.iter().position(|tree| matches!(tree, ParseTree::Node { .. })) {
+            if let ParseTree::Node { children, .. } = form[pos] {
+                form.splice(pos..=pos, children);
+            }
+            result.push(form.iter().map(|tree| tree.symbol()).collect());
+        }
+        result
+    }
+
+    /// Splices the nodes of the `helpers` nonterminals into their parents, so
+    /// that the tree and its derivations read as the EBNF rules were written
+    pub fn flatten(&self, helpers: &BTreeSet<Nonterm>) -> ParseTree {
+        match self {
+            ParseTree::Node { nonterm, prod, children } => {
+                let mut new_children = Vec::new();
+                for child in children {
+                    match child.flatten(helpers) {
+                        ParseTree::Node { nonterm, children, .. } if helpers.contains(&nonterm) => {
+                            new_children.extend(children);
+                        }
+                        child => new_children.push(child),
+                    }
+                }
+                ParseTree::Node { nonterm: *nonterm, prod: prod.clone(), children: new_children }
+            }
+            ParseTree::Leaf { .. } => self.clone(),
+        }
+    }
+
+    /// One node per line, children indented under their parent
+    pub fn pretty(&self, names: &SymbolNames) -> String {
+        fn go(tree: &ParseTree, depth: usize, names: &SymbolNames, result: &mut String) {
+            result.push_str(&"  ".repeat(depth));
+            match tree {
+                ParseTree::Node { prod, children, .. } => {
+                    result.push_str(&names.production(prod));
+                    result.push('\n');
+                    for child in children {
+                        go(child, depth + 1, names, result);
+                    }
+                }
+                ParseTree::Leaf { term, position, .. } => {
+                    result.push_str(&format!(
+                        "{} @{}:{}\n",
+                        names.symbol(Symbol::Term(*term)),
+                        position.line,
+                        position.column,
+                    ));
+                }
+            }
+        }
+        let mut result = String::new();
+        go(self, 0, names, &mut result);
+        result
+    }
+}
+
+impl ParseForest {
+    /// The number of parse trees in the forest, `None` if a cycle makes
+    /// them infinitely many
+    pub fn count(&self) -> Option<usize> {
+        fn go(forest: &ParseForest, node: usize, counts: &mut Vec<Option<Option<usize>>>) -> Option<usize> {
+            if let Some(count) = counts[node] {
+                return count;
+            }
+            // Still being counted: going round a cycle
+            counts[node] = Some(None);
+            let alternatives = &forest.nodes[node].alternatives;
+            let mut count = Some(if alternatives.is_empty() { 1usize } else { 0 });
+            for (_, children) in alternatives {
+                let mut product = Some(1usize);
+                for &child in children {
+                    product = match (product, go(forest, child, counts)) {
+                        (Some(product), Some(child)) => Some(product.saturating_mul(child)),
+                        _ => None,
+                    };
+                }
+                count = match (count, product) {
+                    (Some(count), Some(product)) => Some(count.saturating_add(product)),
+                    _ => None,
+                };
+            }
+            counts[node] = Some(count);
+            count
+        }
+        go(self, self.root, &mut vec![None; self.nodes.len()])
+    }
+
+    /// One of the parse trees in the forest, taking the first derivation
+    /// that does not go round a cycle
+    pub fn tree(&self) -> ParseTree {
+        fn go(forest: &ParseForest, node: usize, path: &mut Vec<usize>) -> Option<ParseTree> {
+            let ForestNode { symbol, start, position, alternatives, .. } = &forest.nodes[node];
+            let nonterm = match *symbol {
+                Symbol::Term(term) => return Some(ParseTree::Leaf { term, index: *start, position: *position }),
+                Symbol::Nonterm(nonterm) => nonterm,
+            };
+            path.push(node);
+            let tree = alternatives.iter().find_map(|(prod, children)| {
+                if children.iter().any(|child| path.contains(child)) {
+                    return None;
+                }
+                let children = children.iter().map(|&child| go(forest, child, path)).collect::<Option<_>>()?;
+                Some(ParseTree::Node { nonterm, prod: prod.clone(), children })
+            });
+            path.pop();
+            tree
+        }
+        go(self, self.root, &mut Vec::new()).expect("Every node derives some tree")
+    }
+
+    /// Every parse tree in the forest, leaving out the derivations going
+    /// round a cycle
+    pub fn trees(&self) -> Vec<ParseTree> {
+        fn go(forest: &ParseForest, node: usize, path: &mut Vec<usize>) -> Vec<ParseTree> {
+            let ForestNode { symbol, start, position, alternatives, .. } = &forest.nodes[node];
+            let nonterm = match *symbol {
+                Symbol::Term(term) => return vec![ParseTree::Leaf { term, index: *start, position: *position }],
+                Symbol::Nonterm(nonterm) => nonterm,
+            };
+            path.push(node);
+            let mut result = Vec::new();
+            for (prod, children) in alternatives {
+                if children.iter().any(|child| path.contains(child)) {
+                    continue;
+                }
+                let mut trees: Vec<Vec<ParseTree>> = vec![vec![]];
+                for &child in children {
+                    let child_trees = go(forest, child, path);
+                    trees = trees
+                        .iter()
+                        .flat_map(|prefix| child_trees.iter().map(move |tree| {
+                            let mut prefix = prefix.clone();
+                            prefix.push(tree.clone());
+                            prefix
+                        }))
+                        .collect();
+                }
+                result.extend(trees.into_iter().map(|children| ParseTree::Node { nonterm, prod: prod.clone(), children }));
+            }
+            path.pop();
+            result
+        }
+        go(self, self.root, &mut Vec::new())
+    }
+}
+
+impl GrammarError {
+    pub fn describe(&self) -> String {
+        format!("line {}: {}", self.line, self.message)
+    }
+}
+
+impl Precedence {
+    pub fn of_term(&self, term: Term) -> Option<(usize, Assoc)> {
+        self.terms.get(&term).cloned()
+    }
+
+    /// The precedence given by `%prec`, or else that of the last terminal of
+    /// the production which has one
+    pub fn of_prod(&self, prod: &Production) -> Option<(usize, Assoc)> {
+        if let Some(&term) = self.prods.get(prod) {
+            return self.of_term(term);
+        }
+        prod.b
+            .iter()
+            .rev()
+            .filter_map(|&sym| match sym {
+                Symbol::Term(t) => self.of_term(t),
+                Symbol::Nonterm(..) => None,
+            })
+            .next()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,11 +482,12 @@ mod tests {
         // A -> a
         // B -> Cb
         // C -> c | #
+        let (a, b, c) = (2, 3, 4);
         let productions = vec![
             Production { s: 0, b: vec![Symbol::Nonterm(1), Symbol::Nonterm(2)] },
-            Production { s: 1, b: vec![Symbol::Term(b'a')] },
-            Production { s: 2, b: vec![Symbol::Nonterm(3), Symbol::Term(b'b')] },
-            Production { s: 3, b: vec![Symbol::Term(b'c')] },
+            Production { s: 1, b: vec![Symbol::Term(a)] },
+            Production { s: 2, b: vec![Symbol::Nonterm(3), Symbol::Term(b)] },
+            Production { s: 3, b: vec![Symbol::Term(c)] },
             Production { s: 3, b: vec![]},
         ];
         let init_nonterm = 0;
@@ -164,15 +497,26 @@ mod tests {
         assert_eq!(grammar.nullable_list.iter().cloned().collect::<Vec<_>>(), vec![3]);
 
         // Test First(A)
-        assert_eq!(grammar.first_map.get(&0).unwrap(), &vec![b'a']);
-        assert_eq!(grammar.first_map.get(&1).unwrap(), &vec![b'a']);
-        assert_eq!(grammar.first_map.get(&2).unwrap(), &vec![b'b', b'c']);
-        assert_eq!(grammar.first_map.get(&3).unwrap(), &vec![b'c']);
+        assert_eq!(grammar.first_map.get(&0).unwrap(), &vec![a]);
+        assert_eq!(grammar.first_map.get(&1).unwrap(), &vec![a]);
+        assert_eq!(grammar.first_map.get(&2).unwrap(), &vec![b, c]);
+        assert_eq!(grammar.first_map.get(&3).unwrap(), &vec![c]);
 
         // Test Follow(A)
         assert_eq!(grammar.follow_map.get(&0).unwrap(), &vec![]);
-        assert_eq!(grammar.follow_map.get(&1).unwrap(), &vec![b'b', b'c']);
+        assert_eq!(grammar.follow_map.get(&1).unwrap(), &vec![b, c]);
         assert_eq!(grammar.follow_map.get(&2).unwrap(), &vec![]);
-        assert_eq!(grammar.follow_map.get(&3).unwrap(), &vec![b'b']);
+        assert_eq!(grammar.follow_map.get(&3).unwrap(), &vec![b]);
+    }
+
+    #[test]
+    fn positions() {
+        let input = b"ab\ncd\n\nef";
+        assert_eq!(Position::of_offset(input, 0), Position { offset: 0, line: 1, column: 1 });
+        assert_eq!(Position::of_offset(input, 2), Position { offset: 2, line: 1, column: 3 });
+        assert_eq!(Position::of_offset(input, 3), Position { offset: 3, line: 2, column: 1 });
+        assert_eq!(Position::of_offset(input, 5), Position { offset: 5, line: 2, column: 3 });
+        assert_eq!(Position::of_offset(input, 7), Position { offset: 7, line: 4, column: 1 });
+        assert_eq!(Position::of_offset(input, 9), Position { offset: 9, line: 4, column: 3 });
     }
-}
\ No newline at end of file
+}
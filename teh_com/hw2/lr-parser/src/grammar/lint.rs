@@ -0,0 +1,168 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::types::*;
+
+impl Grammar {
+    /// Looks for mistakes that make the grammar accept less than it seems
+    /// to, or fail to build for confusing reasons: misspelled or dead
+    /// nonterminals, cycles like `A =>+ A`, and repeated productions.
+    /// Meant for the grammar as written, without `S' -> S $`.
+    pub fn lint(&self) -> Vec<Lint> {
+        let defined: BTreeSet<Nonterm> = self.productions.iter().map(|prod| prod.s).collect();
+        let mut result = Vec::new();
+
+        for &sym in &self.all_symbols {
+            if let Symbol::Nonterm(nonterm) = sym {
+                if !defined.contains(&nonterm) {
+                    result.push(Lint::Undefined(nonterm));
+                }
+            }
+        }
+
+        let mut reachable = BTreeSet::new();
+        let mut stack = vec![self.init_nonterm];
+        while let Some(nonterm) = stack.pop() {
+            if !reachable.insert(nonterm) {
+                continue;
+            }
+            for prod in self.productions.iter().filter(|prod| prod.s == nonterm) {
+                for &sym in &prod.b {
+                    if let Symbol::Nonterm(next) = sym {
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        result.extend(defined.iter().filter(|nonterm| !reachable.contains(nonterm)).map(|&nonterm| Lint::Unreachable(nonterm)));
+
+        let mut productive = BTreeSet::new();
+        loop {
+            let mut change = false;
+            for prod in &self.productions {
+                let derives = prod.b.iter().all(|sym| match sym {
+                    Symbol::Term(..) => true,
+                    Symbol::Nonterm(nonterm) => productive.contains(nonterm),
+                });
+                if derives {
+                    change |= productive.insert(prod.s);
+                }
+            }
+            if !change {
+                break;
+            }
+        }
+        result.extend(defined.iter().filter(|nonterm| !productive.contains(nonterm)).map(|&nonterm| Lint::Unproductive(nonterm)));
+
+        result.extend(self.cycles().into_iter().map(Lint::Cycle));
+
+        let mut seen = BTreeSet::new();
+        let mut reported = BTreeSet::new();
+        for prod in &self.productions {
+            if !seen.insert(prod) && reported.insert(prod) {
+                result.push(Lint::Duplicate(prod.clone()));
+            }
+        }
+        result
+    }
+
+    /// The groups of nonterminals that derive each other alone, the rest of
+    /// the right hand sides deriving ε
+    fn cycles(&self) -> Vec<Vec<Nonterm>> {
+        let mut edges: BTreeMap<Nonterm, BTreeSet<Nonterm>> = BTreeMap::new();
+        for prod in &self.productions {
+            let nullable = |sym: &Symbol| match sym {
+                Symbol::Term(..) => false,
+                Symbol::Nonterm(nonterm) => self.nullable_list.contains(nonterm),
+            };
+            for (pos, sym) in prod.b.iter().enumerate() {
+                if let Symbol::Nonterm(nonterm) = *sym {
+                    if prod.b[..pos].iter().all(nullable) && prod.b[pos + 1..].iter().all(nullable) {
+                        edges.entry(prod.s).or_default().insert(nonterm);
+                    }
+                }
+            }
+        }
+        // Nonterminals each one derives in one step or more
+        let reach: BTreeMap<Nonterm, BTreeSet<Nonterm>> = edges
+            .keys()
+            .map(|&from| {
+                let mut seen = BTreeSet::new();
+                let mut stack: Vec<Nonterm> = edges[&from].iter().cloned().collect();
+                while let Some(nonterm) = stack.pop() {
+                    if seen.insert(nonterm) {
+                        stack.extend(edges.get(&nonterm).into_iter().flatten());
+                    }
+                }
+                (from, seen)
+            })
+            .collect();
+        let mut result: Vec<Vec<Nonterm>> = Vec::new();
+        for (&nonterm, reached) in &reach {
+            if !reached.contains(&nonterm) || result.iter().any(|cycle| cycle.contains(&nonterm)) {
+                continue;
+            }
+            result.push(reached.iter().cloned().filter(|other| reach.get(other).is_some_and(|back| back.contains(&nonterm))).collect());
+        }
+        result
+    }
+}
+
+impl Lint {
+    pub fn describe(&self, names: &SymbolNames) -> String {
+        let nonterm = |nonterm: Nonterm| names.symbol(Symbol::Nonterm(nonterm));
+        match self {
+            Lint::Undefined(n) => format!("{} is used but has no productions", nonterm(*n)),
+            Lint::Unreachable(n) => format!("{} can not be reached from the start symbol", nonterm(*n)),
+            Lint::Unproductive(n) => format!("{} derives no string of terminals", nonterm(*n)),
+            Lint::Cycle(cycle) if cycle.len() == 1 => format!("{0} =>+ {0}", nonterm(cycle[0])),
+            Lint::Cycle(cycle) => format!(
+                "{} derive each other",
+                cycle.iter().map(|&n| nonterm(n)).collect::<Vec<_>>().join(", "),
+            ),
+            Lint::Duplicate(prod) => format!("{} is written more than once", names.production(prod)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::reader::read_grammar;
+
+    fn lint(grammar: &str) -> Vec<String> {
+        let GrammarSpec { productions, names, .. } = read_grammar(grammar).unwrap();
+        Grammar::build(1, productions).lint().iter().map(|lint| lint.describe(&names)).collect()
+    }
+
+    #[test]
+    fn clean_grammar() {
+        assert!(lint(r#"
+            E -> E + T | T
+            T -> T * F | F
+            F -> "(" E ")" | n
+        "#).is_empty());
+    }
+
+    #[test]
+    fn mistakes() {
+        assert_eq!(lint("
+            S -> A b | Exp
+            A -> a | A a
+            Expr -> n
+            L -> L x
+        "), vec![
+            "Exp is used but has no productions",
+            "Expr can not be reached from the start symbol",
+            "L can not be reached from the start symbol",
+            "L derives no string of terminals",
+        ]);
+        assert_eq!(lint("
+            S -> A | s
+            A -> B C | a
+            B -> S
+            C -> ε
+            S -> s
+        "), vec!["S, A, B derive each other", "S -> s is written more than once"]);
+        assert_eq!(lint("S -> S | a"), vec!["S =>+ S"]);
+    }
+}
@@ -0,0 +1,502 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use super::types::*;
+use super::transform::{left_recursion, rules_of};
+
+/// An original production a production in Chomsky normal form comes from,
+/// with the positions of the nullable symbols it leaves out
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Used {
+    prod: usize,
+    omitted: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CnfOrigin {
+    /// Every way of getting the production: the unit productions followed
+    /// first, then the one giving the right hand side, spread over `Rest`
+    /// helpers if it is longer than two
+    Productions(Vec<Vec<Used>>),
+    /// Holds the end of a right hand side longer than two symbols
+    Rest,
+    /// Stands for a terminal next to another symbol
+    Terminal,
+}
+
+/// A grammar in Chomsky or Greibach normal form, made from another one
+pub struct NormalForm {
+    pub grammar: Grammar,
+    /// For each production of `grammar`, the indices of the original
+    /// productions it was made from, none for those standing for a terminal
+    pub sources: Vec<Vec<usize>>,
+    /// Whether the original grammar derives ε, which the normal form can not
+    pub accepts_empty: bool,
+    original: Vec<Production>,
+    cnf: Vec<Production>,
+    cnf_origins: Vec<CnfOrigin>,
+    /// A derivation of ε for every nullable nonterminal, to put back where
+    /// they were left out
+    empty_trees: BTreeMap<Nonterm, ParseTree>,
+    /// The transformations made after reaching Chomsky normal form
+    transformed: Vec<Transformed>,
+}
+
+impl NormalForm {
+    /// The tree of the original grammar a tree of the normal form stands for
+    pub fn translate(&self, tree: &ParseTree) -> ParseTree {
+        self.translate_cnf(&self.untransformed(tree), false).swap_remove(0)
+    }
+
+    /// Every tree of the original grammar a tree of the normal form stands
+    /// for, as several can make the same production of the normal form
+    pub fn translate_all(&self, tree: &ParseTree) -> Vec<ParseTree> {
+        self.translate_cnf(&self.untransformed(tree), true)
+    }
+
+    /// The tree of the original grammar for the empty input, if it derives it
+    pub fn empty_tree(&self) -> Option<ParseTree> {
+        match self.accepts_empty {
+            true => self.empty_trees.get(&self.grammar.init_nonterm).cloned(),
+            false => None,
+        }
+    }
+
+    /// The tree in Chomsky normal form, undoing what was done after it
+    fn untransformed(&self, tree: &ParseTree) -> ParseTree {
+        self.transformed
+            .iter()
+            .rev()
+            .fold(tree.clone(), |tree, transformed| transformed.translate(&tree))
+    }
+
+    /// The trees of the original grammar `tree` stands for, or only the first
+    /// one without `all`
+    fn translate_cnf(&self, tree: &ParseTree, all: bool) -> Vec<ParseTree> {
+        let (prod, children) = match tree {
+            ParseTree::Node { prod, children, .. } => (prod, children),
+            ParseTree::Leaf { .. } => return vec![tree.clone()],
+        };
+        let paths = match self.cnf_origin(prod) {
+            CnfOrigin::Productions(paths) if all => &paths[..],
+            CnfOrigin::Productions(paths) => &paths[..1],
+            _ => panic!("Helpers are translated along with their parent"),
+        };
+        let mut kept = Vec::new();
+        self.gather(children, all, &mut kept);
+        // Every choice of a tree for each symbol kept
+        let mut choices: Vec<Vec<ParseTree>> = vec![Vec::new()];
+        for trees in kept {
+            choices = choices
+                .iter()
+                .flat_map(|choice| trees.iter().map(move |tree| choice.iter().chain(Some(tree)).cloned().collect()))
+                .collect();
+        }
+        let mut result = Vec::new();
+        for path in paths {
+            for choice in &choices {
+                let mut kept = choice.iter().cloned();
+                let mut node = None;
+                for used in path.iter().rev() {
+                    let prod = &self.original[used.prod];
+                    let mut children = Vec::new();
+                    for (pos, &sym) in prod.b.iter().enumerate() {
+                        let child = match sym {
+                            Symbol::Nonterm(nonterm) if used.omitted.contains(&pos) => self.empty_trees[&nonterm].clone(),
+                            _ => node.take().or_else(|| kept.next()).expect("A tree for every symbol kept"),
+                        };
+                        children.push(child);
+                    }
+                    node = Some(ParseTree::Node { nonterm: prod.s, prod: prod.clone(), children });
+                }
+                result.push(node.expect("Productions use at least one original"));
+            }
+        }
+        result
+    }
+
+    /// Translates `children`, splicing in those of the helpers
+    fn gather(&self, children: &[ParseTree], all: bool, result: &mut Vec<Vec<ParseTree>>) {
+        for child in children {
+            match child {
+                ParseTree::Node { prod, children, .. } => match self.cnf_origin(prod) {
+                    CnfOrigin::Rest => self.gather(children, all, result),
+                    CnfOrigin::Terminal => result.push(vec![children[0].clone()]),
+                    CnfOrigin::Productions(..) => result.push(self.translate_cnf(child, all)),
+                },
+                ParseTree::Leaf { .. } => result.push(vec![child.clone()]),
+            }
+        }
+    }
+
+    fn cnf_origin(&self, prod: &Production) -> &CnfOrigin {
+        let index = self.cnf
+            .iter()
+            .position(|other| other == prod)
+            .expect("A production in Chomsky normal form");
+        &self.cnf_origins[index]
+    }
+}
+
+/// Converts `grammar` to Chomsky normal form, where every production is
+/// `A -> B C` or `A -> a`: ε-productions and unit productions are removed,
+/// then useless nonterminals, and longer right hand sides are split with
+/// new nonterminals, and the terminals in them replaced by `T_a -> a`.
+pub fn chomsky_normal_form(grammar: &Grammar, names: &mut SymbolNames) -> NormalForm {
+    let original = grammar.productions.clone();
+
+    // Without ε-productions: every way of leaving out nullable symbols, those
+    // making the same production kept together
+    let mut rules: Vec<(Nonterm, Vec<Symbol>, Vec<Used>)> = Vec::new();
+    let mut seen: BTreeMap<(Nonterm, Vec<Symbol>), usize> = BTreeMap::new();
+    for (index, prod) in original.iter().enumerate() {
+        let nullable: Vec<usize> = (0..prod.b.len())
+            .filter(|&pos| matches!(prod.b[pos], Symbol::Nonterm(nonterm) if grammar.nullable_list.contains(&nonterm)))
+            .collect();
+        for mask in 0..1usize << nullable.len() {
+            let omitted: Vec<usize> = (0..nullable.len()).filter(|bit| mask >> bit & 1 == 1).map(|bit| nullable[bit]).collect();
+            let b: Vec<Symbol> = (0..prod.b.len()).filter(|pos| !omitted.contains(pos)).map(|pos| prod.b[pos]).collect();
+            if b.is_empty() {
+                continue;
+            }
+            let used = Used { prod: index, omitted };
+            match seen.get(&(prod.s, b.clone())) {
+                Some(&rule) => rules[rule].2.push(used),
+                None => {
+                    seen.insert((prod.s, b.clone()), rules.len());
+                    rules.push((prod.s, b, vec![used]));
+                }
+            }
+        }
+    }
+
+    // Without unit productions: A gets the others of every B that A =>+ B,
+    // through every chain of unit productions not going round a cycle
+    let is_unit = |b: &[Symbol]| matches!(b, [Symbol::Nonterm(..)]);
+    let mut order = Vec::new();
+    for (nonterm, ..) in &rules {
+        if !order.contains(nonterm) {
+            order.push(*nonterm);
+        }
+    }
+    let mut seen = BTreeMap::new();
+    let mut unit_free: Vec<(Nonterm, Vec<Symbol>, Vec<Vec<Used>>)> = Vec::new();
+    for &from in &order {
+        let mut paths: BTreeMap<Nonterm, Vec<Vec<Used>>> = vec![(from, vec![Vec::new()])].into_iter().collect();
+        let mut reached = vec![from];
+        let mut queue: VecDeque<(Nonterm, Vec<Nonterm>, Vec<Used>)> = vec![(from, vec![from], Vec::new())].into_iter().collect();
+        while let Some((nonterm, visited, path)) = queue.pop_front() {
+            for (s, b, used) in &rules {
+                if let (true, [Symbol::Nonterm(to)]) = (*s == nonterm, b.as_slice()) {
+                    if visited.contains(to) {
+                        continue;
+                    }
+                    if !paths.contains_key(to) {
+                        reached.push(*to);
+                    }
+                    for used in used {
+                        let path: Vec<Used> = path.iter().chain(Some(used)).cloned().collect();
+                        paths.entry(*to).or_default().push(path.clone());
+                        queue.push_back((*to, visited.iter().chain(Some(to)).cloned().collect(), path));
+                    }
+                }
+            }
+        }
+        for to in reached {
+            for (s, b, used) in &rules {
+                if *s != to || is_unit(b) {
+                    continue;
+                }
+                let rule = *seen.entry((from, b.clone())).or_insert_with(|| {
+                    unit_free.push((from, b.clone(), Vec::new()));
+                    unit_free.len() - 1
+                });
+                for path in &paths[&to] {
+                    for used in used {
+                        unit_free[rule].2.push(path.iter().chain(Some(used)).cloned().collect());
+                    }
+                }
+            }
+        }
+    }
+
+    // Without useless nonterminals
+    let mut productive = BTreeSet::new();
+    loop {
+        let mut change = false;
+        for (s, b, _) in &unit_free {
+            if b.iter().all(|sym| matches!(sym, Symbol::Term(..)) || matches!(sym, Symbol::Nonterm(n) if productive.contains(n))) {
+                change |= productive.insert(*s);
+            }
+        }
+        if !change {
+            break;
+        }
+    }
+    unit_free.retain(|(_, b, _)| b.iter().all(|sym| matches!(sym, Symbol::Term(..)) || matches!(sym, Symbol::Nonterm(n) if productive.contains(n))));
+    let mut reachable = BTreeSet::new();
+    let mut stack = vec![grammar.init_nonterm];
+    while let Some(nonterm) = stack.pop() {
+        if reachable.insert(nonterm) {
+            for (_, b, _) in unit_free.iter().filter(|(s, ..)| *s == nonterm) {
+                stack.extend(b.iter().filter_map(|&sym| match sym {
+                    Symbol::Nonterm(next) => Some(next),
+                    Symbol::Term(..) => None,
+                }));
+            }
+        }
+    }
+    unit_free.retain(|(s, ..)| reachable.contains(s));
+
+    // Terminals and long right hand sides
+    let mut cnf = Vec::new();
+    let mut cnf_origins = Vec::new();
+    let mut sources = Vec::new();
+    let mut terminals: BTreeMap<Term, Nonterm> = BTreeMap::new();
+    let mut terminal_order = Vec::new();
+    for (s, b, paths) in unit_free {
+        let mut source: Vec<usize> = Vec::new();
+        for used in paths.iter().flatten() {
+            if !source.contains(&used.prod) {
+                source.push(used.prod);
+            }
+        }
+        if b.len() == 1 {
+            cnf.push(Production { s, b });
+            cnf_origins.push(CnfOrigin::Productions(paths));
+            sources.push(source);
+            continue;
+        }
+        let mut symbols = Vec::new();
+        for sym in b {
+            let sym = match sym {
+                Symbol::Term(term) => Symbol::Nonterm(*terminals.entry(term).or_insert_with(|| {
+                    let name = format!("T_{}", names.symbol(sym));
+                    let nonterm = names.add_fresh_nonterm(&name);
+                    terminal_order.push((nonterm, term));
+                    nonterm
+                })),
+                Symbol::Nonterm(..) => sym,
+            };
+            symbols.push(sym);
+        }
+        let (mut lhs, mut origin) = (s, CnfOrigin::Productions(paths));
+        let mut rest = symbols.as_slice();
+        while rest.len() > 2 {
+            let name = format!("{}'", names.symbol(Symbol::Nonterm(s)));
+            let helper = names.add_fresh_nonterm(&name);
+            cnf.push(Production { s: lhs, b: vec![rest[0], Symbol::Nonterm(helper)] });
+            cnf_origins.push(origin);
+            sources.push(source.clone());
+            lhs = helper;
+            origin = CnfOrigin::Rest;
+            rest = &rest[1..];
+        }
+        cnf.push(Production { s: lhs, b: rest.to_vec() });
+        cnf_origins.push(origin);
+        sources.push(source);
+    }
+    for (nonterm, term) in terminal_order {
+        cnf.push(Production { s: nonterm, b: vec![Symbol::Term(term)] });
+        cnf_origins.push(CnfOrigin::Terminal);
+        sources.push(Vec::new());
+    }
+
+    let mut empty_trees = BTreeMap::new();
+    loop {
+        let mut change = false;
+        for prod in &original {
+            if empty_trees.contains_key(&prod.s) {
+                continue;
+            }
+            let children: Option<Vec<ParseTree>> = prod.b
+                .iter()
+                .map(|sym| match sym {
+                    Symbol::Nonterm(nonterm) => empty_trees.get(nonterm).cloned(),
+                    Symbol::Term(..) => None,
+                })
+                .collect();
+            if let Some(children) = children {
+                empty_trees.insert(prod.s, ParseTree::Node { nonterm: prod.s, prod: prod.clone(), children });
+                change = true;
+            }
+        }
+        if !change {
+            break;
+        }
+    }
+
+    NormalForm {
+        grammar: Grammar::build(grammar.init_nonterm, cnf.clone()),
+        sources,
+        accepts_empty: grammar.nullable_list.contains(&grammar.init_nonterm),
+        original,
+        cnf,
+        cnf_origins,
+        empty_trees,
+        transformed: Vec::new(),
+    }
+}
+
+/// Converts `grammar` to Greibach normal form, where every production is a
+/// terminal followed by nonterminals. Starts from the Chomsky normal form,
+/// removes left recursion without adding ε-productions, then substitutes
+/// the first nonterminal of every production until it starts with a
+/// terminal.
+pub fn greibach_normal_form(grammar: &Grammar, names: &mut SymbolNames) -> NormalForm {
+    let mut result = chomsky_normal_form(grammar, names);
+    let without_recursion = left_recursion(&result.cnf, names, false);
+    let cnf_order = rules_of(&result.cnf).0;
+    let (order, mut rules) = rules_of(&without_recursion.productions);
+
+    // Left recursion removal leaves every nonterminal starting with a later
+    // one, and the new ones starting with those of the grammar
+    let helpers = order.iter().filter(|nonterm| !cnf_order.contains(nonterm));
+    for &nonterm in cnf_order.iter().rev().chain(helpers) {
+        let mut new_rules = Vec::new();
+        for (b, origin) in rules.remove(&nonterm).unwrap_or_default() {
+            let steps = match origin {
+                Origin::Fragment(steps) => steps,
+                _ => unreachable!("Productions are taken as they are"),
+            };
+            match b[0] {
+                Symbol::Nonterm(first) => {
+                    for (first_b, first_origin) in rules.get(&first).into_iter().flatten() {
+                        let first_steps = match first_origin {
+                            Origin::Fragment(steps) => steps,
+                            _ => unreachable!("Substitutions make fragments"),
+                        };
+                        let new_b = first_b.iter().chain(&b[1..]).cloned().collect();
+                        let new_steps = steps.iter().chain(first_steps).cloned().collect();
+                        new_rules.push((new_b, Origin::Fragment(new_steps)));
+                    }
+                }
+                Symbol::Term(..) => new_rules.push((b, Origin::Fragment(steps))),
+            }
+        }
+        rules.insert(nonterm, new_rules);
+    }
+
+    // Nonterminals only ever at the start of productions are not needed
+    let mut reachable = BTreeSet::new();
+    let mut stack = vec![grammar.init_nonterm];
+    while let Some(nonterm) = stack.pop() {
+        if reachable.insert(nonterm) {
+            for (b, _) in rules.get(&nonterm).into_iter().flatten() {
+                stack.extend(b.iter().filter_map(|&sym| match sym {
+                    Symbol::Nonterm(next) => Some(next),
+                    Symbol::Term(..) => None,
+                }));
+            }
+        }
+    }
+    let mut substituted = Transformed::new(&without_recursion.productions);
+    for nonterm in order.into_iter().filter(|nonterm| reachable.contains(nonterm)) {
+        substituted.add(nonterm, rules.remove(&nonterm).unwrap_or_default());
+    }
+
+    let sources = sources_through(&without_recursion, &result.sources);
+    result.sources = sources_through(&substituted, &sources);
+    result.grammar = Grammar::build(grammar.init_nonterm, substituted.productions.clone());
+    result.transformed = vec![without_recursion, substituted];
+    result
+}
+
+/// The original productions each transformed one comes from, given those
+/// of the productions it was transformed from
+fn sources_through(transformed: &Transformed, sources: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    transformed.origins
+        .iter()
+        .map(|origin| {
+            let steps: &[Step] = match origin {
+                Origin::Fragment(steps) | Origin::Tail(steps) => steps,
+                Origin::TailEnd | Origin::Factored => &[],
+            };
+            let mut result = Vec::new();
+            for step in steps {
+                for &source in &sources[step.prod] {
+                    if !result.contains(&source) {
+                        result.push(source);
+                    }
+                }
+            }
+            result
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::reader::{read_grammar, tokenize};
+    use crate::earley::EarleyParser;
+
+    const EXPRESSIONS: &str = r#"
+        E -> E + T | T
+        T -> T * F | F
+        F -> "(" E ")" | n P | - F
+        P -> p | ε
+    "#;
+
+    fn grammar(text: &str) -> (Grammar, SymbolNames) {
+        let GrammarSpec { productions, names, .. } = read_grammar(text).unwrap();
+        (Grammar::build(1, productions), names)
+    }
+
+    /// Checks that the trees of the normal form translate back to those of
+    /// the original grammar
+    fn same_trees(normal: &NormalForm, grammar: &Grammar, names: &SymbolNames, inputs: &[&str]) {
+        let (original, new) = (EarleyParser::new(grammar), EarleyParser::new(&normal.grammar));
+        for input in inputs {
+            let input = tokenize(input, names).unwrap();
+            let tree = new.parse_tree(&input).unwrap();
+            assert_eq!(normal.translate(&tree), original.parse_tree(&input).unwrap());
+        }
+    }
+
+    #[test]
+    fn chomsky() {
+        // S' is taken by the made up start symbol
+        let (balanced, mut names) = grammar("S -> a S b | ε");
+        let normal = chomsky_normal_form(&balanced, &mut names);
+        let productions: Vec<_> = normal.grammar.productions.iter().map(|prod| names.production(prod)).collect();
+        assert_eq!(productions, vec!["S -> T_a S''", "S'' -> S T_b", "S -> T_a T_b", "T_a -> a", "T_b -> b"]);
+        assert!(normal.accepts_empty);
+        assert_eq!(normal.sources, vec![vec![0], vec![0], vec![0], vec![], vec![]]);
+        same_trees(&normal, &balanced, &names, &["ab", "aaabbb"]);
+
+        let (expressions, mut names) = grammar(EXPRESSIONS);
+        let normal = chomsky_normal_form(&expressions, &mut names);
+        assert!(!normal.accepts_empty);
+        for prod in &normal.grammar.productions {
+            match prod.b.as_slice() {
+                [Symbol::Term(..)] | [Symbol::Nonterm(..), Symbol::Nonterm(..)] => {}
+                _ => panic!("{} is not in Chomsky normal form", names.production(prod)),
+            }
+        }
+        // E -> n, through E -> T, T -> F and F -> n P without P
+        let n = Production { s: 1, b: vec![Symbol::Term(names.find_term("n").unwrap())] };
+        let index = normal.grammar.productions.iter().position(|prod| *prod == n).unwrap();
+        assert_eq!(normal.sources[index], vec![1, 3, 5]);
+        same_trees(&normal, &expressions, &names, &["n", "np", "n+np*n", "(n+-np)*-n"]);
+    }
+
+    #[test]
+    fn greibach() {
+        let (expressions, mut names) = grammar(EXPRESSIONS);
+        let normal = greibach_normal_form(&expressions, &mut names);
+        for prod in &normal.grammar.productions {
+            match prod.b.split_first() {
+                Some((Symbol::Term(..), rest)) if rest.iter().all(|sym| matches!(sym, Symbol::Nonterm(..))) => {}
+                _ => panic!("{} is not in Greibach normal form", names.production(prod)),
+            }
+        }
+        same_trees(&normal, &expressions, &names, &["n", "np", "n+np*n", "(n+-np)*-n", "n*n*n+n+n"]);
+
+        let (indirect, mut names) = grammar("
+            S -> A a | b
+            A -> A c | S d | e
+        ");
+        let normal = greibach_normal_form(&indirect, &mut names);
+        same_trees(&normal, &indirect, &names, &["b", "ea", "bdca", "ecadcada"]);
+    }
+}
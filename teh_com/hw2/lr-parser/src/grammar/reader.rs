@@ -0,0 +1,659 @@
+use std::collections::BTreeSet;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::types::*;
+use crate::lexer::types::Regex;
+
+/// A word of a grammar file line
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Word {
+    Name(String),
+    /// A terminal between double quotes, named by what is inside
+    Quoted(String),
+    Bar,
+    Open,
+    Close,
+    /// `*`, `+` or `?` written right after a symbol or a group
+    Op(char),
+}
+
+/// A symbol, group or repetition on the right hand side of a rule
+enum Item {
+    Sym(Symbol),
+    Group(Vec<Alternative>),
+    Repeat(Box<Item>, char),
+}
+
+#[derive(Default)]
+struct Alternative {
+    items: Vec<Item>,
+    prec: Option<Term>,
+    empty: bool,
+}
+
+const OPS: [char; 3] = ['*', '+', '?'];
+
+fn starts_uppercase(word: &str) -> bool {
+    word.chars().next().is_some_and(|c| c.is_uppercase())
+}
+
+/// Width of the whitespace `text` starts with, in characters
+fn indentation(text: &str) -> usize {
+    text.chars().take_while(|c| c.is_whitespace()).count()
+}
+
+fn is_identifier(word: &str) -> bool {
+    word.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && word.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '\'')
+}
+
+fn error<T>(line: usize, message: String) -> Result<T, GrammarError> {
+    Err(GrammarError { line, message })
+}
+
+/// Takes a `*`, `+` or `?` right after a quoted terminal or a group, unless
+/// it starts a word of its own
+fn glued_op(chars: &mut Peekable<Chars>) -> Option<char> {
+    let op = chars.peek().cloned().filter(|op| OPS.contains(op))?;
+    let mut ahead = chars.clone();
+    ahead.next();
+    match ahead.peek() {
+        Some(&c) if !c.is_whitespace() && !"|()\"#".contains(c) => None,
+        _ => {
+            chars.next();
+            Some(op)
+        }
+    }
+}
+
+/// Splits a line into words, dropping a `#` comment at its end. `|` and
+/// parentheses are words of their own even when written next to others.
+fn split_line(text: &str, line: usize) -> Result<Vec<Word>, GrammarError> {
+    let mut words = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '#' => break,
+            '|' => {
+                chars.next();
+                words.push(Word::Bar);
+            }
+            '(' => {
+                chars.next();
+                words.push(Word::Open);
+            }
+            ')' => {
+                chars.next();
+                words.push(Word::Close);
+                words.extend(glued_op(&mut chars).map(Word::Op));
+            }
+            '"' => {
+                chars.next();
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => name.push(c),
+                            None => return error(line, "unterminated quoted terminal".to_string()),
+                        },
+                        Some(c) => name.push(c),
+                        None => return error(line, "unterminated quoted terminal".to_string()),
+                    }
+                }
+                if name.is_empty() {
+                    return error(line, "empty quoted terminal, write ε for the empty string".to_string());
+                }
+                words.push(Word::Quoted(name));
+                words.extend(glued_op(&mut chars).map(Word::Op));
+            }
+            _ => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "|()\"".contains(c) {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                // `Stmt*` repeats Stmt, while `**` stays a terminal
+                let last = name.chars().last().expect("Words are not empty");
+                if OPS.contains(&last) && is_identifier(&name[..name.len() - 1]) {
+                    name.pop();
+                    words.push(Word::Name(name));
+                    words.push(Word::Op(last));
+                } else {
+                    words.push(Word::Name(name));
+                }
+            }
+        }
+    }
+    Ok(words)
+}
+
+/// The pattern after `prefix` on a line like `NUMBER = [0-9]+` or `%skip \s+`,
+/// up to a `#` following whitespace
+fn pattern_after<'a>(text: &'a str, prefix: &str) -> &'a str {
+    let start = text.find(prefix).expect("The line starts with the prefix") + prefix.len();
+    let pattern = &text[start..];
+    let end = pattern
+        .char_indices()
+        .find(|&(i, c)| c == '#' && pattern[..i].ends_with(char::is_whitespace))
+        .map_or(pattern.len(), |(i, _)| i);
+    pattern[..end].trim()
+}
+
+struct Reader {
+    spec: GrammarSpec,
+    /// Names declared to be terminals
+    tokens: BTreeSet<String>,
+    level: usize,
+    /// The left hand side of the rule being read, and its words so far
+    /// together with their lines
+    rule: Option<(Nonterm, Vec<(Word, usize)>)>,
+    /// Indentation of the line the rule starts on; deeper lines go on with it
+    rule_indent: usize,
+    /// Productions of the helper nonterminals, kept apart so that the first
+    /// production still gives the start symbol
+    helper_prods: Vec<Production>,
+}
+
+impl Reader {
+    fn finish_rule(&mut self) -> Result<(), GrammarError> {
+        let (s, words) = match self.rule.take() {
+            Some(rule) => rule,
+            None => return Ok(()),
+        };
+        for alternative in self.alternatives(&mut words.iter(), None)? {
+            let b = alternative.items.iter().map(|item| self.symbol(item)).collect();
+            let prod = Production { s, b };
+            if let Some(prec) = alternative.prec {
+                self.spec.precedence.prods.insert(prod.clone(), prec);
+            }
+            self.spec.productions.push(prod);
+        }
+        Ok(())
+    }
+
+    fn terminal(&mut self, word: &Word, line: usize) -> Result<Term, GrammarError> {
+        match word {
+            Word::Name(name) if starts_uppercase(name) && !self.tokens.contains(name) => {
+                error(line, format!("{} is a nonterminal, not a terminal", name))
+            }
+            Word::Name(name) | Word::Quoted(name) => Ok(self.spec.names.add_term(name)),
+            _ => error(line, "expected a terminal".to_string()),
+        }
+    }
+
+    fn directive(&mut self, name: &str, words: &[Word], line: usize) -> Result<(), GrammarError> {
+        // Operators declared on later lines bind tighter
+        let assoc = match name {
+            "%left" => Assoc::Left,
+            "%right" => Assoc::Right,
+            "%nonassoc" => Assoc::Nonassoc,
+            "%token" => return Ok(()),
+            _ => return error(line, format!("unknown directive {}", name)),
+        };
+        self.level += 1;
+        for word in words {
+            let term = self.terminal(word, line)?;
+            self.spec.precedence.terms.insert(term, (self.level, assoc));
+        }
+        Ok(())
+    }
+
+    /// Reads alternatives up to the end of the rule, or up to the `)` closing
+    /// the group opened on line `open`
+    fn alternatives<'a, I>(&mut self, words: &mut I, open: Option<usize>) -> Result<Vec<Alternative>, GrammarError>
+    where
+        I: Iterator<Item = &'a (Word, usize)>,
+    {
+        let mut alternatives = vec![Alternative::default()];
+        while let Some((word, line)) = words.next() {
+            let line = *line;
+            let ends_alternative = matches!(word, Word::Bar | Word::Close);
+            if !ends_alternative && alternatives.last().unwrap().prec.is_some() {
+                return error(line, "%prec must come last in an alternative".to_string());
+            }
+            let item = match word {
+                Word::Bar => {
+                    alternatives.push(Alternative::default());
+                    continue;
+                }
+                Word::Close => match open {
+                    Some(..) => return Ok(alternatives),
+                    None => return error(line, "unmatched )".to_string()),
+                },
+                Word::Open => Item::Group(self.alternatives(words, Some(line))?),
+                Word::Op(op) => match alternatives.last_mut().unwrap().items.pop() {
+                    Some(item) => Item::Repeat(Box::new(item), *op),
+                    None => return error(line, format!("nothing to repeat with {}", op)),
+                },
+                Word::Name(name) if name == "%prec" => {
+                    if open.is_some() {
+                        return error(line, "%prec cannot be used inside a group".to_string());
+                    }
+                    let prec = match words.next() {
+                        Some((word, _)) => self.terminal(word, line)?,
+                        None => return error(line, "%prec must be followed by a terminal".to_string()),
+                    };
+                    alternatives.last_mut().unwrap().prec = Some(prec);
+                    continue;
+                }
+                Word::Name(name) if name == "ε" || name == "%empty" => {
+                    let alternative = alternatives.last_mut().unwrap();
+                    if !alternative.items.is_empty() {
+                        return error(line, "ε must be alone in an alternative".to_string());
+                    }
+                    alternative.empty = true;
+                    continue;
+                }
+                Word::Name(name) if name.starts_with('%') => {
+                    return error(line, format!("unknown directive {}", name));
+                }
+                Word::Name(name) if name == "error" => Item::Sym(Symbol::Term(ERROR_TERM)),
+                Word::Name(name) if starts_uppercase(name) && !self.tokens.contains(name) => {
+                    Item::Sym(Symbol::Nonterm(self.spec.names.add_nonterm(name)))
+                }
+                Word::Name(name) | Word::Quoted(name) => Item::Sym(Symbol::Term(self.spec.names.add_term(name))),
+            };
+            let alternative = alternatives.last_mut().unwrap();
+            if alternative.empty {
+                return error(line, "ε must be alone in an alternative".to_string());
+            }
+            alternative.items.push(item);
+        }
+        match open {
+            Some(open) => error(open, "unclosed (".to_string()),
+            None => Ok(alternatives),
+        }
+    }
+
+    /// How `item` is written, which names its helper nonterminal
+    fn render(&self, item: &Item) -> String {
+        match item {
+            Item::Sym(Symbol::Term(t)) => {
+                let name = self.spec.names.symbol(Symbol::Term(*t));
+                match starts_uppercase(&name) && !self.tokens.contains(&name) {
+                    true => format!("{:?}", name),
+                    false => name,
+                }
+            }
+            Item::Sym(sym) => self.spec.names.symbol(*sym),
+            Item::Group(alternatives) => match alternatives.as_slice() {
+                [alternative] if alternative.items.len() == 1 => self.render(&alternative.items[0]),
+                _ => {
+                    let alternatives: Vec<String> = alternatives
+                        .iter()
+                        .map(|alternative| match alternative.items.is_empty() {
+                            true => "ε".to_string(),
+                            false => alternative.items
+                                .iter()
+                                .map(|item| self.render(item))
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                        })
+                        .collect();
+                    format!("({})", alternatives.join(" | "))
+                }
+            },
+            Item::Repeat(inner, op) => format!("{}{}", self.render(inner), op),
+        }
+    }
+
+    /// The symbol standing for `item`, adding a helper nonterminal the first
+    /// time a group or repetition is met
+    fn symbol(&mut self, item: &Item) -> Symbol {
+        match item {
+            Item::Sym(sym) => return *sym,
+            Item::Group(alternatives) if alternatives.len() == 1 && alternatives[0].items.len() == 1 => {
+                return self.symbol(&alternatives[0].items[0]);
+            }
+            _ => {}
+        }
+        let name = self.render(item);
+        if let Some(helper) = self.spec.names.find_nonterm(&name) {
+            return Symbol::Nonterm(helper);
+        }
+        let helper = self.spec.names.add_nonterm(&name);
+        self.spec.helpers.insert(helper);
+        let mut bodies = Vec::new();
+        match item {
+            Item::Group(alternatives) => {
+                for alternative in alternatives {
+                    bodies.push(alternative.items.iter().map(|item| self.symbol(item)).collect());
+                }
+            }
+            Item::Repeat(inner, op) => {
+                let inner = self.symbol(inner);
+                let helper = Symbol::Nonterm(helper);
+                // Lists are left recursive, so LR parsers reduce as they go
+                match op {
+                    '*' => bodies.extend(vec![vec![], vec![helper, inner]]),
+                    '+' => bodies.extend(vec![vec![inner], vec![helper, inner]]),
+                    _ => bodies.extend(vec![vec![], vec![inner]]),
+                }
+            }
+            Item::Sym(..) => unreachable!("Symbols need no helper"),
+        }
+        for b in bodies {
+            self.helper_prods.push(Production { s: helper, b });
+        }
+        Symbol::Nonterm(helper)
+    }
+
+    fn line(&mut self, text: &str, line: usize) -> Result<(), GrammarError> {
+        let first_two: Vec<&str> = text.split_whitespace().take(2).collect();
+        match first_two.as_slice() {
+            [name, "="] if !name.starts_with('#') => {
+                if self.rule.is_some() && indentation(text) > self.rule_indent {
+                    return error(line, format!("{} = is indented into the production above, write token definitions at its level", name));
+                }
+                self.finish_rule()?;
+                let term = self.spec.names.add_term(name);
+                return self.token_rule(Some(term), pattern_after(text, "="), line);
+            }
+            ["%skip", ..] => {
+                self.finish_rule()?;
+                return self.token_rule(None, pattern_after(text, "%skip"), line);
+            }
+            _ => {}
+        }
+        let words = split_line(text, line)?;
+        match words.first() {
+            None => Ok(()),
+            Some(Word::Name(name)) if name.starts_with('%') && name != "%empty" && name != "%prec" => {
+                self.finish_rule()?;
+                self.directive(name, &words[1..], line)
+            }
+            Some(Word::Name(name)) if words.get(1) == Some(&Word::Name("->".to_string())) => {
+                self.finish_rule()?;
+                if !starts_uppercase(name) || self.tokens.contains(name) {
+                    return error(line, format!("the left hand side must be a nonterminal, not {}", name));
+                }
+                let s = self.spec.names.add_nonterm(name);
+                self.rule_indent = indentation(text);
+                self.rule = Some((s, words[2..].iter().map(|word| (word.clone(), line)).collect()));
+                Ok(())
+            }
+            Some(..) => match self.rule.as_mut() {
+                Some((_, rule_words)) => {
+                    rule_words.extend(words.into_iter().map(|word| (word, line)));
+                    Ok(())
+                }
+                None => error(line, "expected a production, like A -> x y".to_string()),
+            },
+        }
+    }
+
+    fn token_rule(&mut self, term: Option<Term>, pattern: &str, line: usize) -> Result<(), GrammarError> {
+        if let Err(regex_error) = Regex::parse(pattern) {
+            return error(line, regex_error.describe());
+        }
+        self.spec.tokens.push(TokenRule { term, pattern: pattern.to_string() });
+        Ok(())
+    }
+}
+
+/// Reads a grammar like
+///
+/// ```text
+/// # Comments start with #
+/// NUMBER = [0-9]+
+/// %skip [ \t\n]+
+/// %left "+"
+/// E -> E "+" E
+///    | "(" E ")"
+///    | NUMBER
+/// Args -> (E ("," E)*)?
+/// ```
+///
+/// Words starting with an uppercase letter are nonterminals, unless declared
+/// by a `%token` line or defined by a regular expression; every other word is
+/// a terminal, so `id`, `while` and `==` are all single terminals, and any
+/// terminal can be written between double quotes. A line that does not start
+/// a rule or a directive goes on with the rule above it. `%skip` lines give
+/// text the lexer drops between tokens. The first production gives the start
+/// symbol, and `S'` is kept for the augmented one.
+///
+/// Parentheses group symbols, so they have to be quoted to be terminals, and
+/// `*`, `+` and `?` right after a symbol or a group repeat it. Each group and
+/// repetition becomes a helper nonterminal named as it is written, listed in
+/// `GrammarSpec::helpers`.
+pub fn read_grammar(input: &str) -> Result<GrammarSpec, GrammarError> {
+    let mut reader = Reader {
+        spec: GrammarSpec::default(),
+        tokens: BTreeSet::new(),
+        level: 0,
+        rule: None,
+        rule_indent: 0,
+        helper_prods: Vec::new(),
+    };
+    reader.spec.names.add_nonterm("S'");
+
+    // Tokens may be declared after their first use. Like `Reader::line`, this
+    // keeps track of the rule being read, since a deeper indented `x = y`
+    // is no definition.
+    let mut rule_indent = None;
+    for (i, text) in input.lines().enumerate() {
+        let declared = match text.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [_, "->", ..] => {
+                rule_indent = Some(indentation(text));
+                continue;
+            }
+            ["%token", ..] => split_line(text, i + 1)?.split_off(1),
+            [name, "=", ..] if !name.starts_with('#') => match rule_indent {
+                Some(indent) if indentation(text) > indent => continue,
+                _ => vec![Word::Name(name.to_string())],
+            },
+            [word, ..] if word.starts_with('%') && *word != "%empty" && *word != "%prec" => {
+                rule_indent = None;
+                continue;
+            }
+            _ => continue,
+        };
+        rule_indent = None;
+        for word in declared {
+            match word {
+                Word::Name(name) => {
+                    reader.spec.names.add_term(&name);
+                    reader.tokens.insert(name);
+                }
+                _ => return error(i + 1, "tokens are declared by name".to_string()),
+            }
+        }
+    }
+
+    for (i, text) in input.lines().enumerate() {
+        reader.line(text, i + 1)?;
+    }
+    reader.finish_rule()?;
+    if reader.spec.productions.is_empty() {
+        return error(input.lines().count().max(1), "the grammar has no productions".to_string());
+    }
+    reader.spec.productions.append(&mut reader.helper_prods);
+    Ok(reader.spec)
+}
+
+/// Splits `input` into the terminals of `names` written as they are named,
+/// taking the longest one at each point and skipping whitespace in between.
+/// Returns where no terminal matches, if anywhere.
+pub fn tokenize(input: &str, names: &SymbolNames) -> Result<Vec<Lexeme>, Position> {
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut start = Position { offset: 0, line: 1, column: 1 };
+    while start.offset < bytes.len() {
+        let rest = &input[start.offset..];
+        let trimmed = rest.trim_start();
+        if trimmed.len() < rest.len() {
+            start = start.advance(&rest.as_bytes()[..rest.len() - trimmed.len()]);
+            continue;
+        }
+        let longest = names.terms
+            .iter()
+            .enumerate()
+            .filter(|&(term, name)| term != END_TERM && term != ERROR_TERM && rest.starts_with(name.as_str()))
+            .max_by_key(|(_, name)| name.len());
+        match longest {
+            Some((term, name)) => {
+                let end = start.advance(name.as_bytes());
+                tokens.push(Lexeme { term, value: name.clone(), start, end });
+                start = end;
+            }
+            None => return Err(start),
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_terminals() {
+        let GrammarSpec { productions, names, precedence, .. } = read_grammar("
+            %token IDENT NUMBER
+            %left ==
+            Stmt -> while Expr do Stmt
+            Stmt -> IDENT := Expr
+            Expr -> Expr == Expr
+            Expr -> IDENT
+            Expr -> NUMBER
+        ").unwrap();
+        let term = |name| Symbol::Term(names.find_term(name).unwrap());
+        let nonterm = |name| Symbol::Nonterm(names.find_nonterm(name).unwrap());
+        assert_eq!(productions[0].b, vec![term("while"), nonterm("Expr"), term("do"), nonterm("Stmt")]);
+        assert_eq!(productions[1].b, vec![term("IDENT"), term(":="), nonterm("Expr")]);
+        assert_eq!(precedence.of_term(names.find_term("==").unwrap()), Some((1, Assoc::Left)));
+        assert_eq!(names.nonterms, vec!["S'", "Stmt", "Expr"]);
+        assert_eq!(&names.terms[..4], &["$", "error", "IDENT", "NUMBER"]);
+
+        let tokens = tokenize("while x==1 do\n  y:=x", &names);
+        assert_eq!(tokens, Err(Position { offset: 6, line: 1, column: 7 }));
+        let tokens = tokenize("while IDENT==NUMBER do\n  IDENT:=IDENT", &names).unwrap();
+        let terms: Vec<_> = tokens.iter().map(|token| names.terms[token.term].as_str()).collect();
+        assert_eq!(terms, vec!["while", "IDENT", "==", "NUMBER", "do", "IDENT", ":=", "IDENT"]);
+        assert_eq!(tokens[5].start, Position { offset: 25, line: 2, column: 3 });
+        assert_eq!(tokens[5].end, Position { offset: 30, line: 2, column: 8 });
+    }
+
+    #[test]
+    fn token_definitions() {
+        let spec = read_grammar("
+            %skip [ \\t\\n]+
+            E -> E + NUMBER
+            E -> NUMBER
+            NUMBER = [0-9]+|0x[0-9a-f]+
+        ").unwrap();
+        let number = spec.names.find_term("NUMBER").unwrap();
+        assert_eq!(spec.productions[1].b, vec![Symbol::Term(number)]);
+        assert_eq!(spec.tokens, vec![
+            TokenRule { term: None, pattern: "[ \\t\\n]+".to_string() },
+            TokenRule { term: Some(number), pattern: "[0-9]+|0x[0-9a-f]+".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn syntax() {
+        let spec = read_grammar(r##"
+            # Statements
+            %token IDENT
+            Stmt -> "IF" Cond "THEN" Stmt   # uppercase terminals need quotes
+                  | IDENT ":=" IDENT
+                  | ε
+            Cond -> IDENT == IDENT|IDENT "|" IDENT
+                  | "#" %prec ==
+            List -> List "," Stmt | %empty
+            %left ==
+            NUMBER = [0-9]+ # in decimal
+            %skip ( |#)+
+        "##).unwrap();
+        let productions: Vec<_> = spec.productions.iter().map(|prod| spec.names.production(prod)).collect();
+        assert_eq!(productions, vec![
+            "Stmt -> IF Cond THEN Stmt",
+            "Stmt -> IDENT := IDENT",
+            "Stmt -> ε",
+            "Cond -> IDENT == IDENT",
+            "Cond -> IDENT | IDENT",
+            "Cond -> #",
+            "List -> List , Stmt",
+            "List -> ε",
+        ]);
+        let equals = spec.names.find_term("==").unwrap();
+        assert_eq!(spec.precedence.prods.get(&spec.productions[5]), Some(&equals));
+        assert_eq!(spec.precedence.of_prod(&spec.productions[5]), Some((1, Assoc::Left)));
+        assert_eq!(spec.tokens[0].pattern, "[0-9]+");
+        assert_eq!(spec.tokens[1].pattern, "( |#)+");
+    }
+
+    #[test]
+    fn errors() {
+        let error = |input| read_grammar(input).unwrap_err().describe();
+        assert_eq!(error("s -> a"), "line 1: the left hand side must be a nonterminal, not s");
+        assert_eq!(error("\n\nx y"), "line 3: expected a production, like A -> x y");
+        assert_eq!(error("S -> a \"b"), "line 1: unterminated quoted terminal");
+        assert_eq!(error("S -> a\n  | b %prec"), "line 2: %prec must be followed by a terminal");
+        assert_eq!(error("S -> a %prec b c"), "line 1: %prec must come last in an alternative");
+        assert_eq!(error("S -> a ε"), "line 1: ε must be alone in an alternative");
+        assert_eq!(error("S -> a\n%start S"), "line 2: unknown directive %start");
+        assert_eq!(error("%left E\nS -> E"), "line 1: E is a nonterminal, not a terminal");
+        assert_eq!(error("S -> N\nN = [a-"), "line 2: bad pattern [a-: unclosed [ at offset 3");
+        assert_eq!(error("# Nothing yet\n"), "line 1: the grammar has no productions");
+        assert_eq!(error("S -> a\n  (b c\n  | d"), "line 2: unclosed (");
+        assert_eq!(error("S -> a)"), "line 1: unmatched )");
+        assert_eq!(error("S -> (a %prec b)"), "line 1: %prec cannot be used inside a group");
+        assert_eq!(error("S -> ε*"), "line 1: nothing to repeat with *");
+        assert_eq!(
+            error("A -> x\n   x = y"),
+            "line 2: x = is indented into the production above, write token definitions at its level",
+        );
+    }
+
+    #[test]
+    fn ebnf() {
+        let spec = read_grammar(r#"
+            %token IDENT
+            Block -> "{" Stmt* "}"
+            Stmt -> IDENT ("=" | "+=") Expr? ";"
+                  | IDENT "(" (IDENT ("," IDENT)*)? ")" ";"
+            Expr -> IDENT ("," IDENT)+ | "X"*
+            Star -> ** | (a)
+        "#).unwrap();
+        let productions: Vec<_> = spec.productions.iter().map(|prod| spec.names.production(prod)).collect();
+        assert_eq!(productions, vec![
+            "Block -> { Stmt* }",
+            "Stmt -> IDENT (= | +=) Expr? ;",
+            "Stmt -> IDENT ( (IDENT (, IDENT)*)? ) ;",
+            "Expr -> IDENT (, IDENT)+",
+            "Expr -> \"X\"*",
+            "Star -> **",
+            "Star -> a",
+            "Stmt* -> ε",
+            "Stmt* -> Stmt* Stmt",
+            "(= | +=) -> =",
+            "(= | +=) -> +=",
+            "Expr? -> ε",
+            "Expr? -> Expr",
+            "(, IDENT) -> , IDENT",
+            "(, IDENT)* -> ε",
+            "(, IDENT)* -> (, IDENT)* (, IDENT)",
+            "(IDENT (, IDENT)*) -> IDENT (, IDENT)*",
+            "(IDENT (, IDENT)*)? -> ε",
+            "(IDENT (, IDENT)*)? -> (IDENT (, IDENT)*)",
+            "(, IDENT)+ -> (, IDENT)",
+            "(, IDENT)+ -> (, IDENT)+ (, IDENT)",
+            "\"X\"* -> ε",
+            "\"X\"* -> \"X\"* X",
+        ]);
+        let helpers: Vec<_> = spec.helpers.iter().map(|&helper| spec.names.nonterms[helper].as_str()).collect();
+        assert_eq!(helpers, vec![
+            "Stmt*", "(= | +=)", "Expr?", "(IDENT (, IDENT)*)?", "(IDENT (, IDENT)*)",
+            "(, IDENT)*", "(, IDENT)", "(, IDENT)+", "\"X\"*",
+        ]);
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::BTreeMap;
+
+use super::types::*;
+
+pub(super) type Rules = Vec<(Vec<Symbol>, Origin)>;
+
+/// A new nonterminal named after `like`
+fn fresh_nonterm(names: &mut SymbolNames, like: Nonterm) -> Nonterm {
+    let name = format!("{}'", names.symbol(Symbol::Nonterm(like)));
+    names.add_fresh_nonterm(&name)
+}
+
+/// The nonterminals in the order their first production appears, with
+/// their productions
+pub(super) fn rules_of(productions: &[Production]) -> (Vec<Nonterm>, BTreeMap<Nonterm, Rules>) {
+    let mut order = Vec::new();
+    let mut rules: BTreeMap<Nonterm, Rules> = BTreeMap::new();
+    for (index, prod) in productions.iter().enumerate() {
+        if !order.contains(&prod.s) {
+            order.push(prod.s);
+        }
+        let origin = Origin::Fragment(vec![Step { prod: index, tail: false }]);
+        rules.entry(prod.s).or_default().push((prod.b.clone(), origin));
+    }
+    (order, rules)
+}
+
+impl Transformed {
+    pub(super) fn new(original: &[Production]) -> Transformed {
+        Transformed { productions: Vec::new(), origins: Vec::new(), original: original.to_vec() }
+    }
+
+    /// Adds the rules of `nonterm`, but not the productions already there
+    pub(super) fn add(&mut self, nonterm: Nonterm, rules: Rules) {
+        for (b, origin) in rules {
+            let prod = Production { s: nonterm, b };
+            if !self.productions.contains(&prod) {
+                self.productions.push(prod);
+                self.origins.push(origin);
+            }
+        }
+    }
+
+    /// The tree of the original grammar a tree of the transformed one stands
+    /// for, to read derivations back as the grammar was written
+    pub fn translate(&self, tree: &ParseTree) -> ParseTree {
+        let (prod, children) = match tree {
+            ParseTree::Node { prod, children, .. } => (prod, children),
+            ParseTree::Leaf { .. } => return tree.clone(),
+        };
+        match self.origin(prod) {
+            Origin::Fragment(steps) => self.build(steps, None, &mut children.iter()),
+            Origin::Factored => {
+                // Gather the factored prefixes down to the production that
+                // tells which original one was meant
+                let mut raw = Vec::new();
+                let mut children = children;
+                loop {
+                    let (last, prefix) = children.split_last().expect("Factored productions end with a helper");
+                    raw.extend(prefix);
+                    let (prod, last_children) = match last {
+                        ParseTree::Node { prod, children, .. } => (prod, children),
+                        ParseTree::Leaf { .. } => unreachable!("Helpers are nonterminals"),
+                    };
+                    match self.origin(prod) {
+                        Origin::Factored => children = last_children,
+                        Origin::Fragment(steps) => {
+                            raw.extend(last_children);
+                            return self.build(steps, None, &mut raw.into_iter());
+                        }
+                        _ => unreachable!("Left factoring makes no tails"),
+                    }
+                }
+            }
+            Origin::Tail(..) | Origin::TailEnd => panic!("Tails are translated along with the node before them"),
+        }
+    }
+
+    fn origin(&self, prod: &Production) -> &Origin {
+        let index = self.productions
+            .iter()
+            .position(|other| other == prod)
+            .expect("A production of the transformed grammar");
+        &self.origins[index]
+    }
+
+    /// The subtree the `steps` derive, taking the trees of the transformed
+    /// grammar from `raw`. With `first`, the innermost production starts
+    /// with it.
+    fn build<'t>(
+        &self,
+        steps: &[Step],
+        first: Option<ParseTree>,
+        raw: &mut impl Iterator<Item = &'t ParseTree>,
+    ) -> ParseTree {
+        let mut node = first;
+        for step in steps.iter().rev() {
+            let prod = &self.original[step.prod];
+            let mut children: Vec<ParseTree> = node.take().into_iter().collect();
+            while children.len() < prod.b.len() {
+                children.push(self.translate(raw.next().expect("A tree for every symbol")));
+            }
+            let mut built = ParseTree::Node { nonterm: prod.s, prod: prod.clone(), children };
+            if step.tail {
+                built = self.unwind(built, raw.next().expect("The helper follows"));
+            }
+            node = Some(built);
+        }
+        node.expect("Fragments have a production")
+    }
+
+    /// Applies the left recursive productions `tail` derives to `base`
+    fn unwind(&self, base: ParseTree, tail: &ParseTree) -> ParseTree {
+        let (prod, children) = match tail {
+            ParseTree::Node { prod, children, .. } => (prod, children),
+            ParseTree::Leaf { .. } => unreachable!("Helpers are nonterminals"),
+        };
+        match self.origin(prod) {
+            Origin::TailEnd => base,
+            Origin::Tail(steps) => {
+                let mut raw = children.iter();
+                let base = self.build(steps, Some(base), &mut raw);
+                match raw.next() {
+                    Some(tail) => self.unwind(base, tail),
+                    None => base,
+                }
+            }
+            _ => unreachable!("Helpers only have tail productions"),
+        }
+    }
+}
+
+/// Removes direct and indirect left recursion: nonterminals are ordered as
+/// their productions first appear, those starting with an earlier one get
+/// its productions substituted in, and `A -> A α | β` becomes
+/// `A -> β A'`, `A' -> α A' | ε`. Cycles `A -> A` are dropped, and left
+/// recursion hidden behind nullable nonterminals is not removed.
+pub fn remove_left_recursion(productions: &[Production], names: &mut SymbolNames) -> Transformed {
+    left_recursion(productions, names, true)
+}
+
+/// Like `remove_left_recursion`, making `A -> β | β A'`, `A' -> α | α A'`
+/// instead when `empty` is false, so that no ε-production is added
+pub(super) fn left_recursion(productions: &[Production], names: &mut SymbolNames, empty: bool) -> Transformed {
+    let (order, mut rules) = rules_of(productions);
+    let mut helpers = BTreeMap::new();
+    for (i, &nonterm) in order.iter().enumerate() {
+        for &earlier in &order[..i] {
+            let mut new_rules = Vec::new();
+            for (b, origin) in rules.remove(&nonterm).unwrap_or_default() {
+                match origin {
+                    Origin::Fragment(steps) if b.first() == Some(&Symbol::Nonterm(earlier)) => {
+                        for (earlier_b, earlier_origin) in &rules[&earlier] {
+                            let earlier_steps = match earlier_origin {
+                                Origin::Fragment(steps) => steps,
+                                _ => unreachable!("Only helpers have other origins"),
+                            };
+                            let new_b = earlier_b.iter().chain(&b[1..]).cloned().collect();
+                            let new_steps = steps.iter().chain(earlier_steps).cloned().collect();
+                            new_rules.push((new_b, Origin::Fragment(new_steps)));
+                        }
+                    }
+                    origin => new_rules.push((b, origin)),
+                }
+            }
+            rules.insert(nonterm, new_rules);
+        }
+
+        let (recursive, others): (Rules, Rules) = rules[&nonterm]
+            .iter()
+            .cloned()
+            .partition(|(b, _)| b.first() == Some(&Symbol::Nonterm(nonterm)));
+        if recursive.is_empty() {
+            continue;
+        }
+        let helper = fresh_nonterm(names, nonterm);
+        let mut tail_rules = Vec::new();
+        for (b, origin) in recursive.into_iter().filter(|(b, _)| b.len() > 1) {
+            let steps = match origin {
+                Origin::Fragment(steps) => steps,
+                _ => unreachable!("Only helpers have other origins"),
+            };
+            if !empty {
+                tail_rules.push((b[1..].to_vec(), Origin::Tail(steps.clone())));
+            }
+            let new_b = b[1..].iter().cloned().chain(Some(Symbol::Nonterm(helper))).collect();
+            tail_rules.push((new_b, Origin::Tail(steps)));
+        }
+        if empty {
+            tail_rules.push((Vec::new(), Origin::TailEnd));
+        }
+        let mut head_rules = Vec::new();
+        for (mut b, origin) in others {
+            let mut steps = match origin {
+                Origin::Fragment(steps) => steps,
+                _ => unreachable!("Only helpers have other origins"),
+            };
+            if !empty {
+                head_rules.push((b.clone(), Origin::Fragment(steps.clone())));
+            }
+            b.push(Symbol::Nonterm(helper));
+            steps[0].tail = true;
+            head_rules.push((b, Origin::Fragment(steps)));
+        }
+        rules.insert(nonterm, head_rules);
+        helpers.insert(nonterm, (helper, tail_rules));
+    }
+
+    let mut result = Transformed::new(productions);
+    for nonterm in order {
+        result.add(nonterm, rules.remove(&nonterm).unwrap_or_default());
+        if let Some((helper, tail_rules)) = helpers.remove(&nonterm) {
+            result.add(helper, tail_rules);
+        }
+    }
+    result
+}
+
+/// Left factors the productions: `A -> α β1 | α β2` becomes `A -> α A'`,
+/// `A' -> β1 | β2`, taking the longest prefix the productions starting with
+/// the same symbol share, until no two of them do.
+pub fn left_factor(productions: &[Production], names: &mut SymbolNames) -> Transformed {
+    fn factor(nonterm: Nonterm, rules: Rules, names: &mut SymbolNames, result: &mut Transformed) {
+        let mut new_rules: Rules = Vec::new();
+        let mut helpers = Vec::new();
+        let mut done = vec![false; rules.len()];
+        for i in 0..rules.len() {
+            if done[i] {
+                continue;
+            }
+            let first = rules[i].0.first();
+            let group: Vec<usize> = (i..rules.len())
+                .filter(|&j| !done[j] && first.is_some() && rules[j].0.first() == first)
+                .collect();
+            if group.len() < 2 {
+                done[i] = true;
+                new_rules.push(rules[i].clone());
+                continue;
+            }
+            let shortest = group.iter().map(|&j| rules[j].0.len()).min().unwrap_or(0);
+            let prefix = (1..shortest)
+                .take_while(|&len| group.iter().all(|&j| rules[j].0[len] == rules[i].0[len]))
+                .last()
+                .unwrap_or(0) + 1;
+            let helper = fresh_nonterm(names, nonterm);
+            let helper_rules = group
+                .iter()
+                .map(|&j| {
+                    done[j] = true;
+                    (rules[j].0[prefix..].to_vec(), rules[j].1.clone())
+                })
+                .collect();
+            let b = rules[i].0[..prefix].iter().cloned().chain(Some(Symbol::Nonterm(helper))).collect();
+            new_rules.push((b, Origin::Factored));
+            helpers.push((helper, helper_rules));
+        }
+        result.add(nonterm, new_rules);
+        for (helper, helper_rules) in helpers {
+            factor(helper, helper_rules, names, result);
+        }
+    }
+
+    let (order, mut rules) = rules_of(productions);
+    let mut result = Transformed::new(productions);
+    for nonterm in order {
+        factor(nonterm, rules.remove(&nonterm).unwrap_or_default(), names, &mut result);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::reader::{read_grammar, tokenize};
+    use crate::earley::EarleyParser;
+    use crate::ll_grammar::LLGrammar;
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::canonical_lr_state::CanonicalLRState;
+
+    fn productions(transformed: &Transformed, names: &SymbolNames) -> Vec<String> {
+        transformed.productions.iter().map(|prod| names.production(prod)).collect()
+    }
+
+    /// Checks that parsing with the transformed grammar and translating the
+    /// tree back gives the tree of the original grammar
+    fn same_trees(transformed: &Transformed, names: &SymbolNames, inputs: &[&str]) {
+        let original = Grammar::build(1, transformed.original.clone());
+        let new = Grammar::build(1, transformed.productions.clone());
+        let (original, new) = (EarleyParser::new(&original), EarleyParser::new(&new));
+        for input in inputs {
+            let input = tokenize(input, names).unwrap();
+            let tree = new.parse_tree(&input).unwrap();
+            assert_eq!(transformed.translate(&tree), original.parse_tree(&input).unwrap());
+        }
+    }
+
+    #[test]
+    fn direct_left_recursion() {
+        let GrammarSpec { productions: prods, mut names, .. } = read_grammar(r#"
+            E -> E + T | T
+            T -> T * F | F
+            F -> "(" E ")" | n
+        "#).unwrap();
+        let transformed = remove_left_recursion(&prods, &mut names);
+        assert_eq!(productions(&transformed, &names), vec![
+            "E -> T E'", "E' -> + T E'", "E' -> ε",
+            "T -> F T'", "T' -> * F T'", "T' -> ε",
+            "F -> ( E )", "F -> n",
+        ]);
+
+        // LL(1) now, with the trees of the LR parser once translated back
+        let ll = LLGrammar::build(1, transformed.productions.clone()).expect("Should be LL(1)");
+        let lr: LRGrammar<CanonicalLRState> = LRGrammar::build(1, prods).unwrap();
+        for input in &["n", "n+n*n", "(n+n)*n+n"] {
+            let input = tokenize(input, &names).unwrap();
+            let tree = transformed.translate(&ll.parse_tree(&input).unwrap());
+            assert_eq!(tree, lr.parse_tree(&input).unwrap());
+            assert_eq!(tree.rightmost_derivation(), lr.parse(&input).unwrap());
+        }
+    }
+
+    #[test]
+    fn indirect_left_recursion() {
+        let GrammarSpec { productions: prods, mut names, .. } = read_grammar("
+            S -> A a | b
+            A -> A c | S d | e
+        ").unwrap();
+        let transformed = remove_left_recursion(&prods, &mut names);
+        assert_eq!(productions(&transformed, &names), vec![
+            "S -> A a", "S -> b",
+            "A -> b d A'", "A -> e A'", "A' -> c A'", "A' -> a d A'", "A' -> ε",
+        ]);
+        same_trees(&transformed, &names, &["b", "ea", "bdca", "ecadcada"]);
+    }
+
+    #[test]
+    fn left_factoring() {
+        let GrammarSpec { productions: prods, mut names, .. } = read_grammar("
+            A -> a b c | a b d | a e | f | a b
+        ").unwrap();
+        let transformed = left_factor(&prods, &mut names);
+        assert_eq!(productions(&transformed, &names), vec![
+            "A -> a A'", "A -> f",
+            "A' -> b A''", "A' -> e",
+            "A'' -> c", "A'' -> d", "A'' -> ε",
+        ]);
+        same_trees(&transformed, &names, &["abc", "abd", "ae", "f", "ab"]);
+
+        // Both together
+        let GrammarSpec { productions: prods, mut names, .. } = read_grammar("
+            E -> E + n | E + x | n
+        ").unwrap();
+        let without_recursion = remove_left_recursion(&prods, &mut names);
+        let factored = left_factor(&without_recursion.productions, &mut names);
+        assert_eq!(productions(&factored, &names), vec!["E -> n E'", "E' -> + E''", "E' -> ε", "E'' -> n E'", "E'' -> x E'"]);
+        let ll = LLGrammar::build(1, factored.productions.clone()).expect("Should be LL(1)");
+        let input = tokenize("n+x+n", &names).unwrap();
+        let tree = without_recursion.translate(&factored.translate(&ll.parse_tree(&input).unwrap()));
+        assert_eq!(names.symbols(&tree.rightmost_derivation()[1]), "E + n");
+    }
+}
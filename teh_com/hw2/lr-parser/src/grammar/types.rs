@@ -1,9 +1,15 @@
 use std::cmp::{PartialOrd, PartialEq, Ord, Eq};
 use std::collections::{BTreeMap, BTreeSet};
 
-pub type Term = u8;
+/// Index of a terminal in `SymbolNames::terms`
+pub type Term = usize;
 pub type Nonterm = usize;
 
+/// Marks the end of the input
+pub const END_TERM: Term = 0;
+/// Stands for the input skipped while recovering from a syntax error
+pub const ERROR_TERM: Term = 1;
+
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
 pub enum Symbol {
     Term(Term),
@@ -25,4 +31,170 @@ pub struct Grammar {
     pub nullable_list: BTreeSet<Nonterm>,
 
     pub all_symbols: Vec<Symbol>,
-}
\ No newline at end of file
+}
+
+/// A place in the input, lines and columns counting from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A concrete syntax tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTree {
+    Node {
+        nonterm: Nonterm,
+        prod: Production,
+        children: Vec<ParseTree>,
+    },
+    Leaf {
+        term: Term,
+        /// Index of the token in the input
+        index: usize,
+        position: Position,
+    },
+}
+
+/// Every parse of an input, sharing the subtrees they have in common. The
+/// derivations of a symbol over the same tokens are packed in a single node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseForest {
+    pub nodes: Vec<ForestNode>,
+    /// The node of the start symbol over the whole input
+    pub root: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForestNode {
+    pub symbol: Symbol,
+    /// Index of the first token, and of the one after the last
+    pub start: usize,
+    pub end: usize,
+    /// Where the first token starts
+    pub position: Position,
+    /// Every way of deriving the symbol over the tokens: a production and the
+    /// nodes of its right hand side. Terminals have none.
+    pub alternatives: Vec<(Production, Vec<usize>)>,
+}
+
+/// What the parser reads: a terminal found somewhere in the source text
+pub trait Token {
+    fn term(&self) -> Term;
+    fn start(&self) -> Position;
+    /// Where the token stops, just past its last character
+    fn end(&self) -> Position;
+}
+
+/// A token holding any value, by default the text it was read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lexeme<V = String> {
+    pub term: Term,
+    pub value: V,
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Human readable names of the symbols, as they appear in the grammar file.
+/// `terms` always starts with `$` and `error`, at `END_TERM` and `ERROR_TERM`.
+#[derive(Debug, Clone)]
+pub struct SymbolNames {
+    pub nonterms: Vec<String>,
+    pub terms: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+    Nonassoc,
+}
+
+/// Operator precedence, as declared by `%left`, `%right`, `%nonassoc` and `%prec`
+#[derive(Debug, Clone, Default)]
+pub struct Precedence {
+    /// Level (higher binds tighter) and associativity of each declared terminal
+    pub terms: BTreeMap<Term, (usize, Assoc)>,
+    /// Terminal whose precedence the production takes instead of its own
+    pub prods: BTreeMap<Production, Term>,
+}
+
+/// A terminal defined in the grammar file by a regular expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenRule {
+    /// The terminal matched, or `None` for text to skip
+    pub term: Option<Term>,
+    pub pattern: String,
+}
+
+/// Everything read from a grammar file
+#[derive(Debug, Clone, Default)]
+pub struct GrammarSpec {
+    pub productions: Vec<Production>,
+    pub names: SymbolNames,
+    pub precedence: Precedence,
+    /// Token definitions and skip rules, in the order they were written
+    pub tokens: Vec<TokenRule>,
+    /// Nonterminals made up for EBNF groups and repetitions
+    pub helpers: BTreeSet<Nonterm>,
+}
+
+/// A malformed grammar file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarError {
+    /// Counting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+/// A likely mistake in a grammar, found by `Grammar::lint`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lint {
+    /// Used on a right hand side, but with no productions
+    Undefined(Nonterm),
+    /// Not derivable from the start symbol
+    Unreachable(Nonterm),
+    /// Derives no string of terminals
+    Unproductive(Nonterm),
+    /// Nonterminals each deriving the others and themselves, as in `A =>+ A`
+    Cycle(Vec<Nonterm>),
+    /// A production written more than once
+    Duplicate(Production),
+}
+
+/// An original production used by a transformed one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    /// Index in `Transformed::original`
+    pub prod: usize,
+    /// Whether a helper nonterminal follows what the production derives,
+    /// applying left recursive productions on top of it
+    pub tail: bool,
+}
+
+/// How a transformed production was made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// Original productions, outermost first, each one expanded at the first
+    /// symbol of the one before. The transformed right hand side is what
+    /// they derive together.
+    Fragment(Vec<Step>),
+    /// `A' -> α A'`, or `A' -> α` when there are no ε-productions, from
+    /// `A -> A α` (or productions deriving it as in `Fragment`), applied to
+    /// the `A` derived before
+    Tail(Vec<Step>),
+    /// `A' -> ε`, ending the left recursive productions applied
+    TailEnd,
+    /// `A -> α A'` after left factoring, completed by the production of `A'`
+    Factored,
+}
+
+/// Productions rewritten into an equivalent grammar, with where each of
+/// them comes from
+#[derive(Debug, Clone)]
+pub struct Transformed {
+    pub productions: Vec<Production>,
+    pub origins: Vec<Origin>,
+    pub original: Vec<Production>,
+}
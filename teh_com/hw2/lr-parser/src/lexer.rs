@@ -0,0 +1,160 @@
+pub mod types;
+pub mod regex;
+pub mod automaton;
+
+use std::collections::BTreeSet;
+
+use types::*;
+use super::grammar::types::*;
+
+/// A longest-match lexer for the terminals of a grammar. Terminals with a
+/// definition match its regular expression, the others their own name.
+/// Between matches of the same length, terminals matched by name win, so
+/// keywords are not taken for identifiers, and then definitions written first.
+pub struct Lexer {
+    dfa: Dfa,
+    /// What each expression of the automaton stands for, `None` for skipped text
+    rules: Vec<Option<Term>>,
+}
+
+impl Lexer {
+    pub fn build(spec: &GrammarSpec) -> Result<Lexer, RegexError> {
+        let mut literals: BTreeSet<Term> = spec.productions
+            .iter()
+            .flat_map(|prod| &prod.b)
+            .filter_map(|&sym| match sym {
+                Symbol::Term(t) => Some(t),
+                Symbol::Nonterm(..) => None,
+            })
+            .collect();
+        literals.remove(&ERROR_TERM);
+        for rule in &spec.tokens {
+            if let Some(term) = rule.term {
+                literals.remove(&term);
+            }
+        }
+
+        let mut rules = Vec::new();
+        let mut regexes = Vec::new();
+        for term in literals {
+            rules.push(Some(term));
+            regexes.push(Regex::literal(spec.names.terms[term].as_bytes()));
+        }
+        for rule in &spec.tokens {
+            rules.push(rule.term);
+            regexes.push(Regex::parse(&rule.pattern)?);
+        }
+        Ok(Lexer { dfa: Dfa::build(&regexes), rules })
+    }
+
+    /// Splits `input` into tokens holding the text they matched
+    pub fn tokenize(&self, input: &str) -> Result<Vec<Lexeme>, LexError> {
+        let bytes = input.as_bytes();
+        let mut tokens = Vec::new();
+        let mut start = Position { offset: 0, line: 1, column: 1 };
+        while start.offset < bytes.len() {
+            let rest = &bytes[start.offset..];
+            // An empty match would never get anywhere
+            let (len, rule) = match self.dfa.longest_match(rest) {
+                Some((len, rule)) if len > 0 => (len, rule),
+                _ => {
+                    let unexpected = String::from_utf8_lossy(rest).chars().next().unwrap();
+                    return Err(LexError { position: start, unexpected });
+                }
+            };
+            let end = start.advance(&rest[..len]);
+            if let Some(term) = self.rules[rule] {
+                tokens.push(Lexeme {
+                    term,
+                    value: String::from_utf8_lossy(&rest[..len]).into_owned(),
+                    start,
+                    end,
+                });
+            }
+            start = end;
+        }
+        Ok(tokens)
+    }
+}
+
+impl RegexError {
+    pub fn describe(&self) -> String {
+        format!("bad pattern {}: {} at offset {}", self.pattern, self.message, self.offset)
+    }
+}
+
+impl LexError {
+    pub fn describe(&self) -> String {
+        format!(
+            "line {}, column {}: unexpected character {:?}",
+            self.position.line,
+            self.position.column,
+            self.unexpected,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::reader::read_grammar;
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::lalr_state::LALRState;
+
+    fn statements() -> GrammarSpec {
+        read_grammar("
+            %skip [ \\t\\n]+
+            %skip //[^\\n]*
+            IDENT = [a-zA-Z_]\\w*
+            NUMBER = \\d+
+            P -> P S
+            P -> S
+            S -> while E do S
+            S -> IDENT := E ;
+            E -> E < NUMBER
+            E -> IDENT
+            E -> NUMBER
+        ").unwrap()
+    }
+
+    #[test]
+    fn tokenize() {
+        let spec = statements();
+        let lexer = Lexer::build(&spec).unwrap();
+        let tokens = lexer.tokenize("while x<10 do // loop\n  whiley := 1;").unwrap();
+        let terms: Vec<_> = tokens.iter().map(|token| spec.names.terms[token.term].as_str()).collect();
+        // Keywords win over identifiers of the same length only
+        assert_eq!(terms, vec!["while", "IDENT", "<", "NUMBER", "do", "IDENT", ":=", "NUMBER", ";"]);
+        let values: Vec<_> = tokens.iter().map(|token| token.value.as_str()).collect();
+        assert_eq!(values, vec!["while", "x", "<", "10", "do", "whiley", ":=", "1", ";"]);
+        assert_eq!(tokens[5].start, Position { offset: 24, line: 2, column: 3 });
+        assert_eq!(tokens[5].end, Position { offset: 30, line: 2, column: 9 });
+
+        let error = lexer.tokenize("x := 1;\ny := $").unwrap_err();
+        assert_eq!(error.position, Position { offset: 13, line: 2, column: 6 });
+        assert_eq!(error.describe(), "line 2, column 6: unexpected character '$'");
+        // `:` alone is no terminal
+        assert_eq!(lexer.tokenize("x : 1").unwrap_err().unexpected, ':');
+    }
+
+    #[test]
+    fn front_end() {
+        let spec = statements();
+        let lexer = Lexer::build(&spec).unwrap();
+        let parser: LRGrammar<LALRState> = LRGrammar::build(spec.productions[0].s, spec.productions.clone())
+            .expect("Should be LALR(1)");
+        let parse = |input| parser.parse_tree(&lexer.tokenize(input).unwrap());
+        assert!(parse("i := 0; while i < 10 do i := i;").is_ok());
+        let error = parse("while i do\n  x := 0 y := 1;").unwrap_err();
+        assert_eq!(error.position.line, 2);
+        assert_eq!(error.describe(&spec.names), "line 2, column 10: unexpected IDENT, expected one of: do, ;, <");
+    }
+
+    #[test]
+    fn bad_pattern() {
+        let mut spec = statements();
+        spec.tokens[2].pattern = "[a-z".to_string();
+        let error = Lexer::build(&spec).err().unwrap();
+        assert_eq!(error.describe(), "bad pattern [a-z: unclosed [ at offset 4");
+    }
+}
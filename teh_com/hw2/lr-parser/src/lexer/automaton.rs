@@ -0,0 +1,159 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use super::types::*;
+
+#[derive(Default)]
+struct NfaState {
+    eps: Vec<usize>,
+    moves: Vec<(BTreeSet<u8>, usize)>,
+}
+
+/// A Thompson automaton, built fragment by fragment
+#[derive(Default)]
+struct Nfa {
+    states: Vec<NfaState>,
+}
+
+impl Nfa {
+    fn add_state(&mut self) -> usize {
+        self.states.push(NfaState::default());
+        self.states.len() - 1
+    }
+
+    /// Adds the states matching `regex`, returning the first and the last one
+    fn add(&mut self, regex: &Regex) -> (usize, usize) {
+        let start = self.add_state();
+        let end = self.add_state();
+        match regex {
+            Regex::Empty => self.states[start].eps.push(end),
+            Regex::Class(bytes) => self.states[start].moves.push((bytes.clone(), end)),
+            Regex::Concat(parts) => {
+                let mut last = start;
+                for part in parts {
+                    let (part_start, part_end) = self.add(part);
+                    self.states[last].eps.push(part_start);
+                    last = part_end;
+                }
+                self.states[last].eps.push(end);
+            }
+            Regex::Alt(options) => {
+                for option in options {
+                    let (option_start, option_end) = self.add(option);
+                    self.states[start].eps.push(option_start);
+                    self.states[option_end].eps.push(end);
+                }
+            }
+            Regex::Star(inner) | Regex::Plus(inner) | Regex::Optional(inner) => {
+                let (inner_start, inner_end) = self.add(inner);
+                self.states[start].eps.push(inner_start);
+                self.states[inner_end].eps.push(end);
+                if !matches!(regex, Regex::Plus(..)) {
+                    self.states[start].eps.push(end);
+                }
+                if !matches!(regex, Regex::Optional(..)) {
+                    self.states[inner_end].eps.push(inner_start);
+                }
+            }
+        }
+        (start, end)
+    }
+
+    fn eps_closure(&self, states: &mut BTreeSet<usize>) {
+        let mut q: VecDeque<usize> = states.iter().cloned().collect();
+        while let Some(first) = q.pop_front() {
+            for &next in &self.states[first].eps {
+                if states.insert(next) {
+                    q.push_back(next);
+                }
+            }
+        }
+    }
+}
+
+impl Dfa {
+    /// Builds the automaton by the subset construction. A state accepting
+    /// several of the expressions accepts the one coming first in `regexes`.
+    pub fn build(regexes: &[Regex]) -> Dfa {
+        let mut nfa = Nfa::default();
+        let start = nfa.add_state();
+        let mut accepting = BTreeMap::new();
+        for (index, regex) in regexes.iter().enumerate() {
+            let (regex_start, regex_end) = nfa.add(regex);
+            nfa.states[start].eps.push(regex_start);
+            accepting.insert(regex_end, index);
+        }
+
+        let mut first_set = vec![start].into_iter().collect();
+        nfa.eps_closure(&mut first_set);
+        let mut states = Vec::new();
+        let mut index = BTreeMap::new();
+        let mut q = VecDeque::new();
+        index.insert(first_set.clone(), 0);
+        q.push_back(first_set);
+        while let Some(set) = q.pop_front() {
+            let mut next = vec![None; 256];
+            for byte in 0..=u8::MAX {
+                let mut new_set = BTreeSet::new();
+                for &state in &set {
+                    for (bytes, to) in &nfa.states[state].moves {
+                        if bytes.contains(&byte) {
+                            new_set.insert(*to);
+                        }
+                    }
+                }
+                if new_set.is_empty() {
+                    continue;
+                }
+                nfa.eps_closure(&mut new_set);
+                let new_index = index.len();
+                let new_index = *index.entry(new_set.clone()).or_insert_with(|| {
+                    q.push_back(new_set);
+                    new_index
+                });
+                next[byte as usize] = Some(new_index);
+            }
+            let accept = set.iter().filter_map(|state| accepting.get(state)).min().cloned();
+            states.push(DfaState { next, accept });
+        }
+        Dfa { states }
+    }
+
+    /// The length of the longest prefix of `input` accepted, and the
+    /// expression accepting it
+    pub fn longest_match(&self, input: &[u8]) -> Option<(usize, usize)> {
+        let mut state = 0;
+        let mut result = self.states[0].accept.map(|accept| (0, accept));
+        for (len, &byte) in input.iter().enumerate() {
+            state = match self.states[state].next[byte as usize] {
+                Some(state) => state,
+                None => break,
+            };
+            if let Some(accept) = self.states[state].accept {
+                result = Some((len + 1, accept));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_match() {
+        let regexes: Vec<Regex> = vec!["if", "[a-z]+", "[0-9]+(\\.[0-9]+)?", "a*"]
+            .into_iter()
+            .map(|pattern| Regex::parse(pattern).unwrap())
+            .collect();
+        let dfa = Dfa::build(&regexes);
+        assert_eq!(dfa.longest_match(b"if("), Some((2, 0)));
+        assert_eq!(dfa.longest_match(b"iffy"), Some((4, 1)));
+        assert_eq!(dfa.longest_match(b"3.14+"), Some((4, 2)));
+        assert_eq!(dfa.longest_match(b"3.x"), Some((1, 2)));
+        assert_eq!(dfa.longest_match(b"aaa"), Some((3, 1)));
+        // Only a* matches the empty string
+        assert_eq!(dfa.longest_match(b"+"), Some((0, 3)));
+        assert_eq!(Dfa::build(&regexes[..3]).longest_match(b"+"), None);
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeSet;
+
+use super::types::*;
+
+fn digits() -> BTreeSet<u8> {
+    (b'0'..=b'9').collect()
+}
+
+fn word_bytes() -> BTreeSet<u8> {
+    (b'a'..=b'z').chain(b'A'..=b'Z').chain(b'0'..=b'9').chain(Some(b'_')).collect()
+}
+
+fn space_bytes() -> BTreeSet<u8> {
+    vec![b' ', b'\t', b'\n', b'\r', 0x0b, 0x0c].into_iter().collect()
+}
+
+fn complement(bytes: &BTreeSet<u8>) -> BTreeSet<u8> {
+    (0..=u8::MAX).filter(|byte| !bytes.contains(byte)).collect()
+}
+
+impl Regex {
+    /// Matches exactly `text`
+    pub fn literal(text: &[u8]) -> Regex {
+        Regex::Concat(text.iter().map(|&byte| Regex::Class(Some(byte).into_iter().collect())).collect())
+    }
+
+    /// Reads the usual syntax: `|`, `*`, `+`, `?`, groups, `.` (any byte but a
+    /// newline), classes like `[a-z_]` or `[^"]`, and the escapes `\n`, `\t`,
+    /// `\r`, `\d`, `\w`, `\s` and `\` before any special character.
+    pub fn parse(pattern: &str) -> Result<Regex, RegexError> {
+        let mut parser = RegexParser { pattern, bytes: pattern.as_bytes(), pos: 0 };
+        let regex = parser.alt()?;
+        match parser.peek() {
+            None => Ok(regex),
+            Some(..) => Err(parser.error("unmatched )")),
+        }
+    }
+}
+
+struct RegexParser<'a> {
+    pattern: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RegexParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).cloned()
+    }
+
+    fn error(&self, message: &str) -> RegexError {
+        RegexError {
+            pattern: self.pattern.to_string(),
+            offset: self.pos,
+            message: message.to_string(),
+        }
+    }
+
+    fn alt(&mut self) -> Result<Regex, RegexError> {
+        let mut options = vec![self.concat()?];
+        while self.peek() == Some(b'|') {
+            self.pos += 1;
+            options.push(self.concat()?);
+        }
+        Ok(if options.len() == 1 { options.pop().unwrap() } else { Regex::Alt(options) })
+    }
+
+    fn concat(&mut self) -> Result<Regex, RegexError> {
+        let mut parts = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == b'|' || c == b')' {
+                break;
+            }
+            parts.push(self.repeat()?);
+        }
+        Ok(match parts.len() {
+            0 => Regex::Empty,
+            1 => parts.pop().unwrap(),
+            _ => Regex::Concat(parts),
+        })
+    }
+
+    fn repeat(&mut self) -> Result<Regex, RegexError> {
+        let mut regex = self.atom()?;
+        while let Some(c) = self.peek() {
+            regex = match c {
+                b'*' => Regex::Star(Box::new(regex)),
+                b'+' => Regex::Plus(Box::new(regex)),
+                b'?' => Regex::Optional(Box::new(regex)),
+                _ => break,
+            };
+            self.pos += 1;
+        }
+        Ok(regex)
+    }
+
+    fn atom(&mut self) -> Result<Regex, RegexError> {
+        let c = self.peek().expect("concat stops at the end of the pattern");
+        match c {
+            b'(' => {
+                self.pos += 1;
+                let regex = self.alt()?;
+                if self.peek() != Some(b')') {
+                    return Err(self.error("unclosed ("));
+                }
+                self.pos += 1;
+                Ok(regex)
+            }
+            b'[' => {
+                self.pos += 1;
+                self.class().map(Regex::Class)
+            }
+            b'.' => {
+                self.pos += 1;
+                Ok(Regex::Class(complement(&Some(b'\n').into_iter().collect())))
+            }
+            b'*' | b'+' | b'?' => Err(self.error("nothing to repeat")),
+            _ => self.escaped().map(Regex::Class),
+        }
+    }
+
+    /// One byte, or the set of bytes of an escape like `\d`
+    fn escaped(&mut self) -> Result<BTreeSet<u8>, RegexError> {
+        let c = self.peek().expect("Called before the end of the pattern");
+        self.pos += 1;
+        if c != b'\\' {
+            return Ok(Some(c).into_iter().collect());
+        }
+        let escape = match self.peek() {
+            Some(escape) => escape,
+            None => return Err(self.error("escape at the end of the pattern")),
+        };
+        self.pos += 1;
+        Ok(match escape {
+            b'n' => Some(b'\n').into_iter().collect(),
+            b't' => Some(b'\t').into_iter().collect(),
+            b'r' => Some(b'\r').into_iter().collect(),
+            b'd' => digits(),
+            b'w' => word_bytes(),
+            b's' => space_bytes(),
+            b'D' => complement(&digits()),
+            b'W' => complement(&word_bytes()),
+            b'S' => complement(&space_bytes()),
+            _ => Some(escape).into_iter().collect(),
+        })
+    }
+
+    /// The inside of `[...]`, after the opening bracket
+    fn class(&mut self) -> Result<BTreeSet<u8>, RegexError> {
+        let negated = self.peek() == Some(b'^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut bytes = BTreeSet::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unclosed [")),
+                Some(b']') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(..) => {}
+            }
+            let from = self.escaped()?;
+            let is_range = self.peek() == Some(b'-') && self.bytes.get(self.pos + 1).is_some_and(|&c| c != b']');
+            if !is_range {
+                bytes.extend(from);
+                continue;
+            }
+            self.pos += 1;
+            let to = self.escaped()?;
+            match (from.len(), to.len()) {
+                (1, 1) if from.iter().next() <= to.iter().next() => {
+                    let (&from, &to) = (from.iter().next().unwrap(), to.iter().next().unwrap());
+                    bytes.extend(from..=to);
+                }
+                _ => return Err(self.error("bad range")),
+            }
+        }
+        Ok(if negated { complement(&bytes) } else { bytes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(bytes: &[u8]) -> Regex {
+        Regex::Class(bytes.iter().cloned().collect())
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Regex::parse("ab|c*"), Ok(Regex::Alt(vec![
+            Regex::Concat(vec![class(b"a"), class(b"b")]),
+            Regex::Star(Box::new(class(b"c"))),
+        ])));
+        assert_eq!(Regex::parse("[a-c_]+"), Ok(Regex::Plus(Box::new(class(b"abc_")))));
+        assert_eq!(Regex::parse("\\(x?\\)"), Ok(Regex::Concat(vec![
+            class(b"("),
+            Regex::Optional(Box::new(class(b"x"))),
+            class(b")"),
+        ])));
+        assert_eq!(Regex::parse("(|a)"), Ok(Regex::Alt(vec![Regex::Empty, class(b"a")])));
+        match Regex::parse("[^\\n]").unwrap() {
+            Regex::Class(bytes) => assert_eq!(bytes.len(), 255),
+            _ => panic!("Class expected"),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let offset = |pattern| Regex::parse(pattern).unwrap_err().offset;
+        assert_eq!(offset("(ab"), 3);
+        assert_eq!(offset("ab)"), 2);
+        assert_eq!(offset("[a-"), 3);
+        assert_eq!(offset("a|*"), 2);
+        assert_eq!(offset("[z-a]"), 4);
+        assert_eq!(Regex::parse("x\\").unwrap_err().message, "escape at the end of the pattern");
+    }
+}
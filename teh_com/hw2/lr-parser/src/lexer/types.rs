@@ -0,0 +1,47 @@
+use std::collections::BTreeSet;
+
+use crate::grammar::types::Position;
+
+/// A regular expression over the bytes of the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Regex {
+    /// Matches the empty string
+    Empty,
+    /// Matches one of the bytes
+    Class(BTreeSet<u8>),
+    Concat(Vec<Regex>),
+    Alt(Vec<Regex>),
+    Star(Box<Regex>),
+    Plus(Box<Regex>),
+    Optional(Box<Regex>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegexError {
+    pub pattern: String,
+    /// Where in the pattern the error is
+    pub offset: usize,
+    pub message: String,
+}
+
+/// A deterministic automaton recognizing several regular expressions at once
+#[derive(Debug, Clone)]
+pub struct Dfa {
+    /// The start state comes first
+    pub states: Vec<DfaState>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DfaState {
+    /// The state reached on each byte
+    pub next: Vec<Option<usize>>,
+    /// The first of the expressions accepted here, if any
+    pub accept: Option<usize>,
+}
+
+/// Text matched by no token definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    pub position: Position,
+    pub unexpected: char,
+}
@@ -0,0 +1,294 @@
+pub mod types;
+
+use types::*;
+use super::grammar::types::*;
+use super::grammar::position_at;
+use super::lr_grammar::helpers::get_first;
+use super::lr_grammar::types::{GrammarInfo, ParseError};
+
+/// A top-down parser, predicting the production to expand by from the next
+/// terminal alone
+pub struct LLGrammar {
+    grammar: Grammar,
+    table: LLTable,
+}
+
+impl LLGrammar {
+    /// Builds the LL(1) table of `productions`, or returns every entry more
+    /// than one production is predicted for
+    pub fn build(init_nonterm: Nonterm, mut productions: Vec<Production>) -> Result<LLGrammar, LLBuildError> {
+        // Initial production, S' -> S$, so that $ follows the start symbol
+        productions.insert(0, Production { s: 0, b: vec![Symbol::Nonterm(init_nonterm), Symbol::Term(END_TERM)] });
+        let grammar = Grammar::build(init_nonterm, productions);
+
+        let (mut term_count, mut nonterm_count) = (ERROR_TERM + 1, 1);
+        for &sym in &grammar.all_symbols {
+            match sym {
+                Symbol::Term(term) => term_count = term_count.max(term + 1),
+                Symbol::Nonterm(nonterm) => nonterm_count = nonterm_count.max(nonterm + 1),
+            }
+        }
+        let mut cells: Vec<Vec<usize>> = vec![Vec::new(); nonterm_count * term_count];
+        for (index, prod) in grammar.productions.iter().enumerate() {
+            for term in predict(&grammar, prod) {
+                let cell = &mut cells[prod.s * term_count + term];
+                if !cell.contains(&index) {
+                    cell.push(index);
+                }
+            }
+        }
+
+        let mut conflicts = Vec::new();
+        for (i, cell) in cells.iter().enumerate() {
+            if cell.len() < 2 {
+                continue;
+            }
+            let (nonterm, term) = (i / term_count, i % term_count);
+            let productions: Vec<Production> = cell.iter().map(|&prod| grammar.productions[prod].clone()).collect();
+            let by_follow = productions.iter().any(|prod| !get_first(&prod.b, &grammar).0.contains(&term));
+            let kind = if by_follow { LLConflictKind::FirstFollow } else { LLConflictKind::FirstFirst };
+            conflicts.push(LLConflict { nonterm, term, kind, productions });
+        }
+        if !conflicts.is_empty() {
+            return Err(LLBuildError { conflicts });
+        }
+
+        let table = LLTable {
+            productions: grammar.productions.clone(),
+            init_nonterm,
+            term_count,
+            nonterm_count,
+            table: cells.into_iter().map(|cell| cell.first().cloned()).collect(),
+        };
+        Ok(LLGrammar { grammar, table })
+    }
+
+    /// Parses `input`, returning its leftmost derivation from the start symbol
+    pub fn parse<T: Token>(&self, input: &[T]) -> Result<Vec<Vec<Symbol>>, ParseError> {
+        self.table.parse(input)
+    }
+
+    /// Parses `input` into a concrete syntax tree
+    pub fn parse_tree<T: Token>(&self, input: &[T]) -> Result<ParseTree, ParseError> {
+        self.table.parse_tree(input)
+    }
+
+    pub fn get_init_nonterm(&self) -> Nonterm {
+        self.grammar.init_nonterm
+    }
+
+    pub fn table(&self) -> &LLTable {
+        &self.table
+    }
+}
+
+/// The terminals `prod` is chosen on: those its right hand side can start
+/// with, and those that can follow its left hand side if it can be empty
+fn predict(grammar: &Grammar, prod: &Production) -> Vec<Term> {
+    let (mut terms, nullable) = get_first(&prod.b, grammar);
+    if nullable {
+        terms.extend(grammar.follow(prod.s));
+        terms.sort_unstable();
+        terms.dedup();
+    }
+    terms
+}
+
+impl LLTable {
+    /// The index of the production to expand `nonterm` by on `term`
+    pub fn entry(&self, nonterm: Nonterm, term: Term) -> Option<usize> {
+        if term >= self.term_count {
+            return None;
+        }
+        self.table[nonterm * self.term_count + term]
+    }
+
+    /// Parses `input`, returning its leftmost derivation from the start symbol
+    pub fn parse<T: Token>(&self, input: &[T]) -> Result<Vec<Vec<Symbol>>, ParseError> {
+        let expansions = self.run(input)?;
+        let mut form = vec![Symbol::Nonterm(self.init_nonterm)];
+        let mut result = vec![form.clone()];
+        for prod in expansions {
+            let prod = &self.productions[prod];
+            let pos = form
+                .iter()
+                .position(|sym| matches!(sym, Symbol::Nonterm(..)))
+                .expect("Expanding the leftmost nonterminal");
+            form.splice(pos..=pos, prod.b.iter().cloned());
+            result.push(form.clone());
+        }
+        Ok(result)
+    }
+
+    /// Parses `input` into a concrete syntax tree
+    pub fn parse_tree<T: Token>(&self, input: &[T]) -> Result<ParseTree, ParseError> {
+        fn go<T: Token>(table: &LLTable, input: &[T], expansions: &mut std::vec::IntoIter<usize>, index: &mut usize) -> ParseTree {
+            let prod = &table.productions[expansions.next().expect("An expansion per nonterminal")];
+            let children = prod.b
+                .iter()
+                .map(|&sym| match sym {
+                    Symbol::Nonterm(..) => go(table, input, expansions, index),
+                    Symbol::Term(term) => {
+                        *index += 1;
+                        ParseTree::Leaf { term, index: *index - 1, position: position_at(input, *index - 1) }
+                    }
+                })
+                .collect();
+            ParseTree::Node { nonterm: prod.s, prod: prod.clone(), children }
+        }
+        let expansions = self.run(input)?;
+        Ok(go(self, input, &mut expansions.into_iter(), &mut 0))
+    }
+
+    /// The predictive parser: expands the nonterminal on top of the stack by
+    /// the production in the table, and matches terminals against the input.
+    /// Returns the productions expanded, in order.
+    fn run<T: Token>(&self, input: &[T]) -> Result<Vec<usize>, ParseError> {
+        let mut stack = vec![Symbol::Term(END_TERM), Symbol::Nonterm(self.init_nonterm)];
+        let mut expansions = Vec::new();
+        let mut index = 0;
+        while let Some(top) = stack.pop() {
+            let next_input = input.get(index).map_or(END_TERM, |token| token.term());
+            match top {
+                Symbol::Term(term) if term == next_input => index += 1,
+                Symbol::Term(term) => return Err(self.error_at(input, index, vec![term])),
+                Symbol::Nonterm(nonterm) => match self.entry(nonterm, next_input) {
+                    Some(prod) => {
+                        stack.extend(self.productions[prod].b.iter().rev());
+                        expansions.push(prod);
+                    }
+                    None => {
+                        let expected = (0..self.term_count)
+                            .filter(|&term| term != ERROR_TERM && self.entry(nonterm, term).is_some())
+                            .collect();
+                        return Err(self.error_at(input, index, expected));
+                    }
+                },
+            }
+        }
+        Ok(expansions)
+    }
+
+    fn error_at<T: Token>(&self, input: &[T], index: usize, expected: Vec<Term>) -> ParseError {
+        ParseError {
+            position: position_at(input, index),
+            unexpected: input.get(index).map(|token| token.term()),
+            expected,
+        }
+    }
+}
+
+impl LLConflict {
+    pub fn describe(&self, names: &SymbolNames) -> String {
+        let kind = match self.kind {
+            LLConflictKind::FirstFirst => "FIRST/FIRST",
+            LLConflictKind::FirstFollow => "FIRST/FOLLOW",
+        };
+        let mut result = format!(
+            "{} conflict for {} on {}\n",
+            kind,
+            names.symbol(Symbol::Nonterm(self.nonterm)),
+            names.symbol(Symbol::Term(self.term)),
+        );
+        for prod in &self.productions {
+            result.push_str(&format!("  predict: {}\n", names.production(prod)));
+        }
+        result
+    }
+}
+
+impl LLBuildError {
+    pub fn describe(&self, names: &SymbolNames) -> String {
+        self.conflicts
+            .iter()
+            .map(|conflict| conflict.describe(names))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::reader::{read_grammar, tokenize};
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::canonical_lr_state::CanonicalLRState;
+
+    fn ll(grammar: &str) -> (Result<LLGrammar, LLBuildError>, GrammarSpec) {
+        let spec = read_grammar(grammar).unwrap();
+        (LLGrammar::build(1, spec.productions.clone()), spec)
+    }
+
+    const EXPRESSIONS: &str = r#"
+        E -> T E'
+        E' -> + T E' | ε
+        T -> F T'
+        T' -> * F T' | ε
+        F -> "(" E ")" | n
+    "#;
+
+    #[test]
+    fn leftmost_derivation() {
+        let (parser, spec) = ll(EXPRESSIONS);
+        let parser = parser.expect("Should be LL(1)");
+        let names = &spec.names;
+        let derivation: Vec<_> = parser
+            .parse(&tokenize("n+n", names).unwrap())
+            .unwrap()
+            .iter()
+            .map(|form| names.symbols(form))
+            .collect();
+        assert_eq!(derivation, vec![
+            "E", "T E'", "F T' E'", "n T' E'", "n E'", "n + T E'", "n + F T' E'", "n + n T' E'", "n + n E'", "n + n",
+        ]);
+
+        // Same trees as an LR parser, with their leftmost derivations
+        let lr: LRGrammar<CanonicalLRState> = LRGrammar::build(1, spec.productions.clone()).unwrap();
+        for input in &["n", "n*(n+n)*n", "((n))+n"] {
+            let input = tokenize(input, names).unwrap();
+            let tree = lr.parse_tree(&input).unwrap();
+            assert_eq!(parser.parse_tree(&input).unwrap(), tree);
+            assert_eq!(parser.parse(&input).unwrap(), tree.leftmost_derivation());
+        }
+    }
+
+    #[test]
+    fn conflicts() {
+        let (result, spec) = ll("E -> E + n | n");
+        let conflicts = result.err().expect("Left recursion is not LL(1)").conflicts;
+        let names = &spec.names;
+        let (e, n) = (names.find_nonterm("E").unwrap(), names.find_term("n").unwrap());
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0], LLConflict {
+            nonterm: e,
+            term: n,
+            kind: LLConflictKind::FirstFirst,
+            productions: spec.productions[0..2].to_vec(),
+        });
+        assert_eq!(conflicts[0].describe(names), "FIRST/FIRST conflict for E on n\n  predict: E -> E + n\n  predict: E -> n\n");
+
+        let (result, spec) = ll("
+            S -> A a | b
+            A -> a | ε
+        ");
+        let conflicts = result.err().expect("a starts A and follows it").conflicts;
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kind, LLConflictKind::FirstFollow);
+        assert_eq!(conflicts[0].term, spec.names.find_term("a").unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        let (parser, spec) = ll(EXPRESSIONS);
+        let parser = parser.unwrap();
+        let names = &spec.names;
+        let term = |name| names.find_term(name).unwrap();
+        let error = parser.parse(&tokenize("n+*n", names).unwrap()).unwrap_err();
+        assert_eq!(error.position.offset, 2);
+        assert_eq!(error.unexpected, Some(term("*")));
+        assert_eq!(error.expected, vec![term("("), term("n")]);
+        let error = parser.parse(&tokenize("(n", names).unwrap()).unwrap_err();
+        assert_eq!(error.unexpected, None);
+        assert_eq!(error.expected, vec![term(")")]);
+    }
+}
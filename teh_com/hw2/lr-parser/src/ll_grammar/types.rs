@@ -0,0 +1,36 @@
+use super::super::grammar::types::*;
+
+/// The LL(1) parse table: the production to expand each nonterminal by,
+/// given the next terminal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LLTable {
+    /// The productions of the grammar, after the made up `S' -> S $`
+    pub productions: Vec<Production>,
+    pub init_nonterm: Nonterm,
+    pub term_count: usize,
+    pub nonterm_count: usize,
+    /// `term_count` entries per nonterminal, indices in `productions`
+    pub table: Vec<Option<usize>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LLConflictKind {
+    /// The right hand sides can start with the same terminal
+    FirstFirst,
+    /// One of them can be empty and the terminal can follow the nonterminal
+    FirstFollow,
+}
+
+/// A table entry more than one production of a nonterminal is predicted for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LLConflict {
+    pub nonterm: Nonterm,
+    pub term: Term,
+    pub kind: LLConflictKind,
+    pub productions: Vec<Production>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LLBuildError {
+    pub conflicts: Vec<LLConflict>,
+}
@@ -1,17 +1,30 @@
 pub mod types;
 pub mod helpers;
 pub mod states;
+pub mod counterexample;
+pub mod actions;
+pub mod codegen;
+pub mod serialize;
+pub mod glr;
+pub mod dot;
+pub mod report;
 
 use std::collections::BTreeMap;
+use std::fmt;
 
 use types::*;
+use counterexample::CounterexampleFinder;
+use actions::SemanticActions;
 use super::grammar::types::*;
+use super::grammar::position_at;
+
+/// Terminals shifted after an error before errors get reported again
+const RECOVERY_SHIFTS: usize = 3;
 
 pub struct LRGrammar<S: LRState> {
     grammar: Grammar,
     states: Vec<S>,
-    init_state: S,
-    action: BTreeMap<(S, Term), Move<S>>,
+    table: ParseTable,
 }
 
 impl GrammarInfo for Grammar {
@@ -47,88 +60,871 @@ impl GrammarInfo for Grammar {
 }
 
 impl<S: LRState> LRGrammar<S> {
-    pub fn build(init_nonterm: Nonterm, mut productions: Vec<Production>) -> Option<LRGrammar<S>> {
-        // Initial production, S' -> S$
-        productions.insert(0, Production { s: 0, b: vec![Symbol::Nonterm(init_nonterm), Symbol::Term(b'$')] });
-
-        let grammar = Grammar::build(init_nonterm, productions);
-
-        let init_state = <S as LRState>::initial_state(grammar.productions[0].clone(), &grammar);
-        let states = <S as LRState>::all_states(init_state.clone(), &grammar);
-        let mut action = BTreeMap::new();
-        
-        for state in &states {
-            let curr_action = state.action(&grammar);
-            match curr_action {
-                Some(moves) => {
-                    for (term, curr_move) in moves {
-                        action.insert((state.clone(), term), curr_move);
-                    }
-                },
-                None => return None,
+    pub fn build(init_nonterm: Nonterm, productions: Vec<Production>) -> Result<LRGrammar<S>, BuildError> {
+        LRGrammar::build_with_precedence(init_nonterm, productions, &Precedence::default())
+    }
+
+    /// Like `build`, settling shift/reduce conflicts by operator precedence
+    /// the way yacc does
+    pub fn build_with_precedence(
+        init_nonterm: Nonterm,
+        productions: Vec<Production>,
+        precedence: &Precedence,
+    ) -> Result<LRGrammar<S>, BuildError> {
+        let (grammar, init_state, states, glr_table) = automaton(init_nonterm, productions, precedence);
+        let GLRTable { productions, init_state: init_index, term_count, nonterm_count, action, goto } = glr_table;
+        let mut conflicts = Vec::new();
+        for (i, actions) in action.iter().enumerate() {
+            let (index, term) = (i / term_count, i % term_count);
+            if actions.len() > 1 {
+                conflicts.push(Conflict::new(index, &states[index], term, actions, &productions));
             }
         }
 
-        Some(LRGrammar {
+        if !conflicts.is_empty() {
+            let finder = CounterexampleFinder::new(&init_state, &grammar);
+            for conflict in &mut conflicts {
+                conflict.counterexample = Some(finder.find(&states[conflict.state], conflict));
+            }
+            return Err(BuildError { conflicts });
+        }
+
+        let table = ParseTable {
+            productions,
+            init_state: init_index,
+            term_count,
+            nonterm_count,
+            action: action.into_iter().map(|actions| actions.first().cloned()).collect(),
+            goto,
+        };
+        Ok(LRGrammar {
             grammar,
-            init_state,
             states,
-            action,
+            table,
         })
     }
 
-    pub fn parse(&self, mut input: &[u8]) -> Option<Vec<Vec<Symbol>>> {
-        let mut stack = Vec::new();
-        stack.push((self.init_state.clone(), Symbol::Term(b' ')));
-        let mut result = Vec::new();
-        result.push(input.iter().map(|&c| Symbol::Term(c)).collect());
-        println!("{}", String::from_utf8(input.to_vec()).unwrap());
+    /// Parses `input`, returning its rightmost derivation from the start symbol
+    pub fn parse<T: Token>(&self, input: &[T]) -> Result<Vec<Vec<Symbol>>, ParseError> {
+        self.table.parse(input)
+    }
+
+    /// Parses `input` into a concrete syntax tree
+    pub fn parse_tree<T: Token>(&self, input: &[T]) -> Result<ParseTree, ParseError> {
+        self.table.parse_tree(input)
+    }
+
+    /// Parses `input` running `actions` on every shift and reduce, and returns
+    /// the value of the start symbol
+    pub fn parse_with_actions<T: Token, V>(
+        &self,
+        input: &[T],
+        actions: &SemanticActions<T, V>,
+    ) -> Result<V, ParseError> {
+        self.table.parse_with_actions(input, actions)
+    }
+
+    /// Like `parse_tree`, but recovers from syntax errors through the
+    /// productions using the `error` terminal. Returns every error met, and
+    /// the tree if the parser could get to the end of the input.
+    pub fn parse_tree_recovering<T: Token>(&self, input: &[T]) -> (Option<ParseTree>, Vec<ParseError>) {
+        self.table.parse_tree_recovering(input)
+    }
+
+    /// Like `parse_with_actions`, recovering from syntax errors as
+    /// `parse_tree_recovering` does
+    pub fn parse_with_actions_recovering<T: Token, V>(
+        &self,
+        input: &[T],
+        actions: &SemanticActions<T, V>,
+    ) -> (Option<V>, Vec<ParseError>) {
+        self.table.parse_with_actions_recovering(input, actions)
+    }
+
+    pub fn get_init_nonterm(&self) -> Nonterm {
+        self.grammar.init_nonterm
+    }
+
+    pub fn get_states(&self) -> Vec<S> {
+        self.states.clone()
+    }
+
+    pub fn table(&self) -> &ParseTable {
+        &self.table
+    }
+}
+
+impl ParseTable {
+    pub fn state_count(&self) -> usize {
+        self.action.len() / self.term_count
+    }
+
+    pub fn action(&self, state: StateId, term: Term) -> Option<Action> {
+        if term >= self.term_count {
+            return None;
+        }
+        self.action[state * self.term_count + term]
+    }
+
+    pub fn goto(&self, state: StateId, nonterm: Nonterm) -> Option<StateId> {
+        self.goto[state * self.nonterm_count + nonterm]
+    }
+
+    /// The terminals `state` has a move for, with the move
+    pub fn actions(&self, state: StateId) -> impl Iterator<Item = (Term, Action)> + '_ {
+        self.action[state * self.term_count..(state + 1) * self.term_count]
+            .iter()
+            .enumerate()
+            .filter_map(|(term, curr_move)| curr_move.map(|curr_move| (term, curr_move)))
+    }
+
+    /// Parses `input`, returning its rightmost derivation from the start symbol
+    pub fn parse<T: Token>(&self, input: &[T]) -> Result<Vec<Vec<Symbol>>, ParseError> {
+        // The reductions make the rightmost derivation backwards
+        let mut reductions = Vec::new();
+        self.run(input, None, |_, _| (), |prod, _| reductions.push(prod.clone()))?;
+        let mut form = vec![self.productions[0].b[0]];
+        let mut result = vec![form.clone()];
+        for prod in reductions.iter().rev() {
+            let pos = form
+                .iter()
+                .rposition(|sym| matches!(sym, Symbol::Nonterm(..)))
+                .expect("Every reduction has a nonterminal to expand");
+            form.splice(pos..=pos, prod.b.iter().cloned());
+            result.push(form.clone());
+        }
+        Ok(result)
+    }
+
+    /// Parses `input` into a concrete syntax tree
+    pub fn parse_tree<T: Token>(&self, input: &[T]) -> Result<ParseTree, ParseError> {
+        self.run(input, None, |term, index| tree_leaf(input, term, index), tree_node)
+    }
+
+    /// Parses `input` running `actions` on every shift and reduce, and returns
+    /// the value of the start symbol
+    pub fn parse_with_actions<T: Token, V>(
+        &self,
+        input: &[T],
+        actions: &SemanticActions<T, V>,
+    ) -> Result<V, ParseError> {
+        self.run(
+            input,
+            None,
+            |term, index| match term {
+                ERROR_TERM => actions.error(),
+                _ => actions.shift(&input[index]),
+            },
+            |prod, values| actions.reduce(prod, values),
+        )
+    }
+
+    /// Like `parse_tree`, but recovers from syntax errors through the
+    /// productions using the `error` terminal. Returns every error met, and
+    /// the tree if the parser could get to the end of the input.
+    pub fn parse_tree_recovering<T: Token>(&self, input: &[T]) -> (Option<ParseTree>, Vec<ParseError>) {
+        let mut errors = Vec::new();
+        let tree = self.run(input, Some(&mut errors), |term, index| tree_leaf(input, term, index), tree_node);
+        (tree.ok(), errors)
+    }
+
+    /// Like `parse_with_actions`, recovering from syntax errors as
+    /// `parse_tree_recovering` does
+    pub fn parse_with_actions_recovering<T: Token, V>(
+        &self,
+        input: &[T],
+        actions: &SemanticActions<T, V>,
+    ) -> (Option<V>, Vec<ParseError>) {
+        let mut errors = Vec::new();
+        let value = self.run(
+            input,
+            Some(&mut errors),
+            |term, index| match term {
+                ERROR_TERM => actions.error(),
+                _ => actions.shift(&input[index]),
+            },
+            |prod, values| actions.reduce(prod, values),
+        );
+        (value.ok(), errors)
+    }
+
+    /// The LR parsing loop, keeping next to each state a value made by `shift`
+    /// out of a terminal and the index of its token, or by `reduce` out of the values of
+    /// the right hand side. Those are moved into a buffer kept from one
+    /// reduction to the next, for `reduce` to take what it needs. Returns the
+    /// value of the start symbol, or the error that stopped the parser.
+    ///
+    /// Without `errors`, parsing stops at the first error. Otherwise errors
+    /// are recovered from in panic mode, like yacc does: states are popped
+    /// until one can shift `error`, then input is discarded until parsing
+    /// can go on.
+    fn run<T: Token, V, F, G>(
+        &self,
+        input: &[T],
+        mut errors: Option<&mut Vec<ParseError>>,
+        mut shift: F,
+        mut reduce: G,
+    ) -> Result<V, ParseError>
+    where
+        F: FnMut(Term, usize) -> V,
+        G: FnMut(&Production, &mut Vec<V>) -> V,
+    {
+        let mut stack: Vec<(StateId, Option<V>)> = vec![(self.init_state, None)];
+        let mut children = Vec::new();
+        let mut index = 0;
+        // Terminals to shift before reporting errors again
+        let mut err_flag = 0;
         loop {
-            let top_state = stack.last().unwrap().0.clone();
-            let next_input = if input.is_empty() { b'$' } else { input[0] };
-            let curr_move = self.action.get(&(top_state, next_input));
-            match curr_move {
-                Some(curr_move) => match curr_move {
-                    &Move::Shift(ref state_added) => {
-                        stack.push((state_added.clone(), Symbol::Term(next_input)));
-                        if next_input == b'$' {
-                            result.reverse();
-                            return Some(result);
-                        } else {
-                            input = &input[1..];
+            let top_state = stack.last().unwrap().0;
+            let next_input = input.get(index).map_or(END_TERM, |token| token.term());
+            let mut curr_move = self.action(top_state, next_input);
+            if curr_move.is_none() && errors.is_some() {
+                // Reduce what can only be reduced first, so that recovery does
+                // not throw it away
+                curr_move = self.default_reduction(top_state);
+            }
+            let curr_move = match curr_move {
+                Some(curr_move) => curr_move,
+                None => {
+                    let error = self.error_at(top_state, input, index);
+                    let errors = match errors.as_mut() {
+                        Some(errors) => errors,
+                        None => return Err(error),
+                    };
+                    if err_flag == RECOVERY_SHIFTS {
+                        // Nothing shifted since the error, so skip the terminal
+                        if next_input == END_TERM {
+                            return Err(error);
+                        }
+                        index += 1;
+                        continue;
+                    }
+                    if err_flag == 0 {
+                        errors.push(error.clone());
+                    }
+                    loop {
+                        let top_state = stack.last().unwrap().0;
+                        if let Some(Action::Shift(state_added)) = self.action(top_state, ERROR_TERM) {
+                            stack.push((state_added, Some(shift(ERROR_TERM, index))));
+                            break;
                         }
-                    },
-                    &Move::Reduce(ref prod) => {
-                        for _ in 0..prod.b.len() {
-                            if stack.pop().is_none() {
-                                panic!("Number of states on the stack too small");
-                            }
+                        if stack.len() == 1 {
+                            return Err(error);
                         }
-                        let state_added = stack.last()
-                            .expect("The stack should not be empty")
-                            .0
-                            .go_to(Symbol::Nonterm(prod.s), &self.grammar);
-                        stack.push((state_added, Symbol::Nonterm(prod.s)));
-
-                        let mut stack_symbols = stack
-                            .iter()
-                            .skip(1)
-                            .map(|&(_, sym)| sym)
-                            .collect::<Vec<_>>();
-                        stack_symbols.extend(input.iter().map(|&c| Symbol::Term(c)));
-                        result.push(stack_symbols);
+                        stack.pop();
                     }
+                    err_flag = RECOVERY_SHIFTS;
+                    continue;
+                }
+            };
+            match curr_move {
+                Action::Shift(state_added) => {
+                    if next_input == END_TERM {
+                        let (_, value) = stack.pop().expect("The stack should not be empty");
+                        return Ok(value.expect("Accepting after the start symbol"));
+                    }
+                    stack.push((state_added, Some(shift(next_input, index))));
+                    index += 1;
+                    err_flag = err_flag.saturating_sub(1);
+                }
+                Action::Reduce(prod) => {
+                    let prod = &self.productions[prod];
+                    // Tables loaded from a file may pop the initial state, or
+                    // miss the goto to follow
+                    let bottom = match stack.len().checked_sub(prod.b.len()) {
+                        Some(bottom) if bottom > 0 => bottom,
+                        _ => return Err(self.error_at(top_state, input, index)),
+                    };
+                    let state_added = match self.goto(stack[bottom - 1].0, prod.s) {
+                        Some(state_added) => state_added,
+                        None => return Err(self.error_at(top_state, input, index)),
+                    };
+                    children.extend(
+                        stack.drain(bottom..).map(|(_, value)| value.expect("Only the initial state has no value")),
+                    );
+                    let value = reduce(prod, &mut children);
+                    children.clear();
+                    stack.push((state_added, Some(value)));
+                }
+            }
+        }
+    }
+
+    fn error_at<T: Token>(&self, state: StateId, input: &[T], index: usize) -> ParseError {
+        let expected = self.actions(state)
+            .map(|(term, _)| term)
+            .filter(|&term| term != ERROR_TERM)
+            .collect();
+        ParseError {
+            position: position_at(input, index),
+            unexpected: input.get(index).map(|token| token.term()),
+            expected,
+        }
+    }
+
+    /// The reduction made by `state` on every terminal it has a move for, if any
+    fn default_reduction(&self, state: StateId) -> Option<Action> {
+        let mut moves = self.actions(state).map(|(_, curr_move)| curr_move);
+        let first = moves.next()?;
+        match first {
+            Action::Reduce(..) if moves.all(|curr_move| curr_move == first) => Some(first),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `state` only has `S' -> S $ .`, so that the parser never enters
+/// it
+fn is_accepted<S: LRState>(state: &S) -> bool {
+    state.items().iter().all(|item| item.prod.s == 0 && item.pos == item.prod.b.len())
+}
+
+/// Builds the automaton of `productions` after adding `S' -> S $`, and
+/// numbers its states. Every move that precedence does not settle is kept.
+fn automaton<S: LRState>(
+    init_nonterm: Nonterm,
+    mut productions: Vec<Production>,
+    precedence: &Precedence,
+) -> (Grammar, S, Vec<S>, GLRTable) {
+    // Initial production, S' -> S$
+    productions.insert(0, Production { s: 0, b: vec![Symbol::Nonterm(init_nonterm), Symbol::Term(END_TERM)] });
+
+    let grammar = Grammar::build(init_nonterm, productions);
+
+    let init_state = <S as LRState>::initial_state(grammar.productions[0].clone(), &grammar);
+    // The state without items, where every other one goes on an error, is
+    // left out: no move leads to it. The one entered by shifting $ comes
+    // last, as the parser accepts instead, so the others are numbered the
+    // way textbooks do.
+    let (mut states, accepted): (Vec<S>, Vec<S>) = <S as LRState>::all_states(init_state.clone(), &grammar)
+        .into_iter()
+        .filter(|state| !state.items().is_empty())
+        .partition(|state| !is_accepted(state));
+    states.extend(accepted);
+    let states_index: BTreeMap<&S, StateId> = states.iter().enumerate().map(|(i, state)| (state, i)).collect();
+    let mut prod_index = BTreeMap::new();
+    for (i, prod) in grammar.productions.iter().enumerate() {
+        prod_index.entry(prod).or_insert(i);
+    }
+    let (mut term_count, mut nonterm_count) = (ERROR_TERM + 1, 1);
+    for &sym in &grammar.all_symbols {
+        match sym {
+            Symbol::Term(term) => term_count = term_count.max(term + 1),
+            Symbol::Nonterm(nonterm) => nonterm_count = nonterm_count.max(nonterm + 1),
+        }
+    }
+    let mut table = GLRTable {
+        productions: grammar.productions.clone(),
+        init_state: states_index[&init_state],
+        term_count,
+        nonterm_count,
+        action: vec![Vec::new(); states.len() * term_count],
+        goto: vec![None; states.len() * nonterm_count],
+    };
+
+    for (index, state) in states.iter().enumerate() {
+        let mut moves: BTreeMap<Term, Vec<Move<S>>> = BTreeMap::new();
+        for (term, curr_move) in state.action(&grammar) {
+            let term_moves = moves.entry(term).or_default();
+            if !term_moves.contains(&curr_move) {
+                term_moves.push(curr_move);
+            }
+        }
+        for (term, term_moves) in moves {
+            let term_moves = match resolve(term, term_moves, precedence) {
+                Ok(curr_move) => curr_move.into_iter().collect(),
+                Err(term_moves) => term_moves,
+            };
+            // Empty for a nonassociative operator used twice, an error
+            table.action[index * term_count + term] = term_moves
+                .into_iter()
+                .map(|curr_move| match curr_move {
+                    Move::Shift(to) => Action::Shift(states_index[&to]),
+                    Move::Reduce(prod) => Action::Reduce(prod_index[&prod]),
+                })
+                .collect();
+        }
+        for &sym in &grammar.all_symbols {
+            if let Symbol::Nonterm(nonterm) = sym {
+                let to = state.go_to(sym, &grammar);
+                if !to.items().is_empty() {
+                    table.goto[index * nonterm_count + nonterm] = Some(states_index[&to]);
+                }
+            }
+        }
+    }
+    (grammar, init_state, states, table)
+}
+
+fn tree_leaf<T: Token>(input: &[T], term: Term, index: usize) -> ParseTree {
+    ParseTree::Leaf { term, index, position: position_at(input, index) }
+}
+
+fn tree_node(prod: &Production, children: &mut Vec<ParseTree>) -> ParseTree {
+    ParseTree::Node { nonterm: prod.s, prod: prod.clone(), children: std::mem::take(children) }
+}
+
+/// Picks the move to make on `term`, returning all of them back if they
+/// conflict and precedence does not settle it
+fn resolve<S: LRState>(
+    term: Term,
+    mut moves: Vec<Move<S>>,
+    precedence: &Precedence,
+) -> Result<Option<Move<S>>, Vec<Move<S>>> {
+    if moves.len() == 1 {
+        return Ok(moves.pop());
+    }
+    if moves.len() != 2 {
+        return Err(moves);
+    }
+    let reduce_prec = match (&moves[0], &moves[1]) {
+        (Move::Shift(..), Move::Reduce(prod)) | (Move::Reduce(prod), Move::Shift(..)) => {
+            precedence.of_prod(prod)
+        }
+        _ => return Err(moves),
+    };
+    let (shift_level, assoc) = match precedence.of_term(term) {
+        Some(shift_prec) => shift_prec,
+        None => return Err(moves),
+    };
+    let reduce_level = match reduce_prec {
+        Some((reduce_level, _)) => reduce_level,
+        None => return Err(moves),
+    };
+    let keep_shift = if shift_level != reduce_level {
+        shift_level > reduce_level
+    } else {
+        match assoc {
+            Assoc::Left => false,
+            Assoc::Right => true,
+            Assoc::Nonassoc => return Ok(None),
+        }
+    };
+    Ok(moves.into_iter().find(|curr_move| matches!(curr_move, Move::Shift(..)) == keep_shift))
+}
+
+impl Item {
+    pub fn describe(&self, names: &SymbolNames) -> String {
+        let mut result = names.symbol(Symbol::Nonterm(self.prod.s));
+        result.push_str(" ->");
+        for (i, &sym) in self.prod.b.iter().enumerate() {
+            if i == self.pos {
+                result.push_str(" .");
+            }
+            result.push(' ');
+            result.push_str(&names.symbol(sym));
+        }
+        if self.pos == self.prod.b.len() {
+            result.push_str(" .");
+        }
+        if !self.lookaheads.is_empty() {
+            let lookaheads = self.lookaheads
+                .iter()
+                .map(|&t| names.symbol(Symbol::Term(t)))
+                .collect::<Vec<_>>();
+            result.push_str(&format!(", [{}]", lookaheads.join(" ")));
+        }
+        result
+    }
+}
+
+/// Symbols are written by number, as `SymbolNames::symbol` does for those
+/// it has no name for
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.describe(&SymbolNames { nonterms: Vec::new(), terms: Vec::new() }))
+    }
+}
+
+impl Conflict {
+    fn new<S: LRState>(
+        state_index: usize,
+        state: &S,
+        term: Term,
+        actions: &[Action],
+        productions: &[Production],
+    ) -> Conflict {
+        let items = state.items();
+        let shifts: Vec<Item> = items
+            .iter()
+            .filter(|item| item.prod.b.get(item.pos) == Some(&Symbol::Term(term)))
+            .cloned()
+            .collect();
+        let reductions = actions
+            .iter()
+            .filter_map(|curr_move| match curr_move {
+                Action::Reduce(prod) => Some(productions[*prod].clone()),
+                Action::Shift(..) => None,
+            })
+            .collect();
+        let kind = if shifts.is_empty() { ConflictKind::ReduceReduce } else { ConflictKind::ShiftReduce };
+        Conflict {
+            state: state_index,
+            items,
+            term,
+            kind,
+            shifts,
+            reductions,
+            counterexample: None,
+        }
+    }
+
+    pub fn describe(&self, names: &SymbolNames) -> String {
+        let kind = match self.kind {
+            ConflictKind::ShiftReduce => "shift/reduce",
+            ConflictKind::ReduceReduce => "reduce/reduce",
+        };
+        let mut result = format!(
+            "{} conflict in state {} on {}\n",
+            kind,
+            self.state,
+            names.symbol(Symbol::Term(self.term)),
+        );
+        result.push_str("  items:\n");
+        for item in &self.items {
+            result.push_str(&format!("    {}\n", item.describe(names)));
+        }
+        for item in &self.shifts {
+            result.push_str(&format!("  shift:  {}\n", item.describe(names)));
+        }
+        for prod in &self.reductions {
+            result.push_str(&format!("  reduce: {}\n", names.production(prod)));
+        }
+        if let Some(counterexample) = &self.counterexample {
+            result.push_str(&counterexample.describe(names));
+        }
+        result
+    }
+}
+
+impl BuildError {
+    pub fn describe(&self, names: &SymbolNames) -> String {
+        self.conflicts
+            .iter()
+            .map(|conflict| conflict.describe(names))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl LoadError {
+    pub fn describe(&self) -> String {
+        format!("bad parse tables: {}", self.message)
+    }
+}
+
+impl ParseError {
+    pub fn describe(&self, names: &SymbolNames) -> String {
+        let describe_term = |term: Option<Term>| match term {
+            Some(END_TERM) | None => "end of input".to_string(),
+            Some(term) => names.symbol(Symbol::Term(term)),
+        };
+        let expected = self.expected
+            .iter()
+            .map(|&term| describe_term(Some(term)))
+            .collect::<Vec<_>>();
+        format!(
+            "line {}, column {}: unexpected {}, expected one of: {}",
+            self.position.line,
+            self.position.column,
+            describe_term(self.unexpected),
+            expected.join(", "),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use states::canonical_lr_state::CanonicalLRState;
+    use states::lalr_state::LALRState;
+    use crate::grammar::reader::{read_grammar, tokenize};
+
+    fn operator_grammar() -> GrammarSpec {
+        read_grammar("
+            %nonassoc <
+            %left + -
+            %left *
+            %right ^
+            E -> E + E
+            E -> E * E
+            E -> E ^ E
+            E -> E < E
+            E -> - E %prec ^
+            E -> n
+        ").unwrap()
+    }
+
+    fn tokens(input: &str, names: &SymbolNames) -> Vec<Lexeme> {
+        tokenize(input, names).expect("Every character should be a terminal")
+    }
+
+    fn term(name: &str, names: &SymbolNames) -> Term {
+        names.find_term(name).expect("No such terminal")
+    }
+
+    fn e_op_e(op: &str, names: &SymbolNames) -> Vec<Symbol> {
+        vec![Symbol::Nonterm(1), Symbol::Term(term(op, names)), Symbol::Nonterm(1)]
+    }
+
+    #[test]
+    fn precedence_settles_conflicts() {
+        let GrammarSpec { productions, names, precedence, .. } = operator_grammar();
+        assert!(LRGrammar::<CanonicalLRState>::build(1, productions.clone()).is_err());
+        let parser: LRGrammar<CanonicalLRState> = LRGrammar::build_with_precedence(1, productions, &precedence)
+            .expect("Precedence should settle every conflict");
+
+        // The rightmost derivation starts with the operator reduced last
+        let derivation = parser.parse(&tokens("n+n*n", &names)).unwrap();
+        assert_eq!(derivation[1], e_op_e("+", &names));
+        let derivation = parser.parse(&tokens("n*n+n", &names)).unwrap();
+        assert_eq!(derivation[1], e_op_e("+", &names));
+        let derivation = parser.parse(&tokens("-n^n", &names)).unwrap();
+        assert_eq!(derivation[1], vec![Symbol::Term(term("-", &names)), Symbol::Nonterm(1)]);
+    }
+
+    #[test]
+    fn associativity() {
+        let GrammarSpec { productions, names, precedence, .. } = operator_grammar();
+        let parser: LRGrammar<LALRState> = LRGrammar::build_with_precedence(1, productions, &precedence)
+            .expect("Precedence should settle every conflict");
+        let parse = |input| parser.parse(&tokens(input, &names));
+
+        // E => E + E => E + n => E + E + n
+        let derivation = parse("n+n+n").unwrap();
+        assert_eq!(names.symbols(&derivation[2]), "E + n");
+        assert_eq!(names.symbols(&derivation[3]), "E + E + n");
+        // E => E ^ E => E ^ E ^ E
+        let derivation = parse("n^n^n").unwrap();
+        assert_eq!(names.symbols(&derivation[2]), "E ^ E ^ E");
+        assert!(parse("n<n").is_ok());
+        assert!(parse("n+n<n*n").is_ok());
+        assert!(parse("n<n<n").is_err());
+    }
+
+    #[test]
+    fn parse_tree() {
+        let GrammarSpec { productions: prods, names, precedence, .. } = operator_grammar();
+        let parser: LRGrammar<LALRState> = LRGrammar::build_with_precedence(1, prods.clone(), &precedence)
+            .expect("Precedence should settle every conflict");
+        let leaf = |name, index| ParseTree::Leaf {
+            term: term(name, &names),
+            index,
+            position: Position { offset: index, line: 1, column: index + 1 },
+        };
+        let number = |index| ParseTree::Node {
+            nonterm: 1,
+            prod: prods[5].clone(),
+            children: vec![leaf("n", index)],
+        };
+        assert_eq!(parser.parse_tree(&tokens("n+n*n", &names)), Ok(ParseTree::Node {
+            nonterm: 1,
+            prod: prods[0].clone(),
+            children: vec![
+                number(0),
+                leaf("+", 1),
+                ParseTree::Node {
+                    nonterm: 1,
+                    prod: prods[1].clone(),
+                    children: vec![number(2), leaf("*", 3), number(4)],
                 },
-                None => { return None },
+            ],
+        }));
+        let input = tokens("-n^n*n", &names);
+        let terms: Vec<_> = input.iter().map(|token| token.term).collect();
+        assert_eq!(parser.parse_tree(&input).unwrap().leaves(), terms);
+        assert_eq!(parser.parse(&input), parser.parse_tree(&input).map(|tree| tree.rightmost_derivation()));
+        assert!(parser.parse_tree(&tokens("n+", &names)).is_err());
+    }
+
+    #[test]
+    fn parse_tree_empty_production() {
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            S -> A B
+            A -> a
+            B -> C b
+            C -> c
+            C ->
+        ").unwrap();
+        let parser: LRGrammar<CanonicalLRState> = LRGrammar::build(1, productions.clone())
+            .expect("Should be LR(1)");
+        let tree = parser.parse_tree(&tokens("ab", &names)).unwrap();
+        match tree {
+            ParseTree::Node { nonterm: 1, children, .. } => match &children[1] {
+                ParseTree::Node { nonterm: 3, children, .. } => {
+                    assert_eq!(children[0], ParseTree::Node { nonterm: 4, prod: productions[4].clone(), children: vec![] });
+                    assert_eq!(children[1], ParseTree::Leaf {
+                        term: term("b", &names),
+                        index: 1,
+                        position: Position { offset: 1, line: 1, column: 2 },
+                    });
+                }
+                _ => panic!("B expected"),
+            },
+            _ => panic!("S expected"),
+        }
+    }
+
+    fn statement_grammar() -> (LRGrammar<LALRState>, Vec<Production>, SymbolNames) {
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            P -> P T
+            P -> T
+            T -> n ;
+            T -> error ;
+        ").unwrap();
+        let parser = LRGrammar::build(1, productions.clone()).expect("Should be LALR(1)");
+        (parser, productions, names)
+    }
+
+    fn summary<'a>(errors: &[ParseError], names: &'a SymbolNames) -> Vec<(usize, Option<&'a str>)> {
+        errors
+            .iter()
+            .map(|error| (error.position.offset, error.unexpected.map(|term| names.terms[term].as_str())))
+            .collect()
+    }
+
+    #[test]
+    fn dense_tables() {
+        let (parser, productions, names) = statement_grammar();
+        let table = parser.table();
+        assert_eq!(table.state_count(), parser.get_states().len());
+        assert_eq!(table.productions[1..], productions[..]);
+        // No row is kept for the state without items
+        let canonical: LRGrammar<CanonicalLRState> = LRGrammar::build(1, productions.clone()).unwrap();
+        let canonical = canonical.table();
+        assert!((0..canonical.state_count()).all(|state| canonical.actions(state).next().is_some()));
+
+        // P -> P T is reduced after n ; n ;
+        let n = term("n", &names);
+        let after_p = table.goto(table.init_state, 1).expect("The start symbol has a goto");
+        let after_n = match table.action(after_p, n) {
+            Some(Action::Shift(state)) => state,
+            other => panic!("Shift expected, found {:?}", other),
+        };
+        assert_eq!(table.action(after_n, n), None);
+        assert_eq!(table.action(table.init_state, table.term_count), None);
+        for state in 0..table.state_count() {
+            for (term, curr_move) in table.actions(state) {
+                assert_eq!(table.action(state, term), Some(curr_move));
+                if let Action::Shift(to) = curr_move {
+                    assert!(to < table.state_count());
+                }
             }
         }
     }
 
-    pub fn get_init_nonterm(&self) -> Nonterm {
-        self.grammar.init_nonterm
+    #[test]
+    fn error_recovery() {
+        let (parser, _, names) = statement_grammar();
+        assert!(parser.parse_tree(&tokens("n;nn;n;", &names)).is_err());
+
+        let (tree, errors) = parser.parse_tree_recovering(&tokens("n;nn;n;", &names));
+        assert_eq!(summary(&errors, &names), vec![(3, Some("n"))]);
+        // The bad statement becomes T -> error;
+        let tree = tree.expect("Should recover");
+        assert_eq!(names.symbols(&tree.leaves().into_iter().map(Symbol::Term).collect::<Vec<_>>()), "n ; error ; n ;");
+
+        let (tree, errors) = parser.parse_tree_recovering(&tokens("nn;n;n;;", &names));
+        assert!(tree.is_some());
+        assert_eq!(summary(&errors, &names), vec![
+            (1, Some("n")),
+            (7, Some(";")),
+        ]);
+
+        let (tree, errors) = parser.parse_tree_recovering(&tokens("n;;", &names));
+        assert!(tree.is_some());
+        assert_eq!(summary(&errors, &names), vec![(2, Some(";"))]);
     }
 
-    pub fn get_states(&self) -> Vec<S> {
-        self.states.clone()
+    #[test]
+    fn error_recovery_counts_statements() {
+        let (parser, productions, names) = statement_grammar();
+        let mut actions = SemanticActions::new(|_: &Lexeme| (0, 0), || (0, 0));
+        actions
+            .on(productions[0].clone(), |v| (v[0].0 + v[1].0, v[0].1 + v[1].1))
+            .on(productions[2].clone(), |_| (1, 0))
+            .on(productions[3].clone(), |_| (0, 1));
+        // (good statements, bad statements)
+        let (value, errors) = parser.parse_with_actions_recovering(&tokens("n;n;nnn;n;;n;", &names), &actions);
+        assert_eq!(value, Some((4, 2)));
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn unrecoverable_errors() {
+        // Error at the end of the input
+        let (parser, _, names) = statement_grammar();
+        let (tree, errors) = parser.parse_tree_recovering(&tokens("n;n", &names));
+        assert!(tree.is_none());
+        assert_eq!(summary(&errors, &names), vec![(3, None)]);
+
+        // No production uses error
+        let GrammarSpec { productions, names, precedence, .. } = operator_grammar();
+        let parser: LRGrammar<CanonicalLRState> = LRGrammar::build_with_precedence(1, productions, &precedence)
+            .expect("Precedence should settle every conflict");
+        let (tree, errors) = parser.parse_tree_recovering(&tokens("n+*n", &names));
+        assert!(tree.is_none());
+        assert_eq!(summary(&errors, &names), vec![(2, Some("*"))]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_error() {
+        let GrammarSpec { productions, names, precedence, .. } = operator_grammar();
+        let parser: LRGrammar<CanonicalLRState> = LRGrammar::build_with_precedence(1, productions, &precedence)
+            .expect("Precedence should settle every conflict");
+        let error = parser.parse(&tokens("n+n*+n", &names)).unwrap_err();
+        assert_eq!(error.position, Position { offset: 4, line: 1, column: 5 });
+        assert_eq!(error.unexpected, Some(term("+", &names)));
+        assert_eq!(error.expected, vec![term("-", &names), term("n", &names)]);
+
+        let error = parser.parse(&tokens("n+n*", &names)).unwrap_err();
+        assert_eq!(error.position.offset, 4);
+        assert_eq!(error.unexpected, None);
+        assert_eq!(error.expected, vec![term("-", &names), term("n", &names)]);
+
+        // After a complete expression, any operator or the end of the input
+        let error = parser.parse(&tokens("n-", &names)).unwrap_err();
+        assert_eq!(error.position.column, 2);
+        assert_eq!(
+            error.describe(&names),
+            "line 1, column 2: unexpected -, expected one of: end of input, <, +, *, ^",
+        );
+
+        let error = parser.parse(&tokens("n\n+\n  +n", &names)).unwrap_err();
+        assert_eq!(error.position, Position { offset: 6, line: 3, column: 3 });
+        assert_eq!(parser.parse::<Lexeme>(&[]).unwrap_err().position, Position { offset: 0, line: 1, column: 1 });
+    }
+
+    #[test]
+    fn reduce_reduce_is_not_settled() {
+        let GrammarSpec { productions, precedence, .. } = read_grammar("
+            %left n
+            S -> A
+            S -> B
+            A -> n
+            B -> n
+        ").unwrap();
+        let result = LRGrammar::<CanonicalLRState>::build_with_precedence(1, productions, &precedence);
+        let conflicts = result.err().expect("Should not be LR(1)").conflicts;
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kind, ConflictKind::ReduceReduce);
+    }
+
+    #[test]
+    fn ebnf_tree() {
+        let GrammarSpec { productions, names, helpers, .. } = read_grammar(r#"
+            Block -> "{" Stmt* "}"
+            Stmt -> x ("=" | "+=") (x ("," x)*)? ";"
+        "#).unwrap();
+        let parser: LRGrammar<LALRState> = LRGrammar::build(1, productions).expect("Should be LALR(1)");
+        let tree = parser.parse_tree(&tokens("{ x = x, x; x +=; }", &names)).unwrap().flatten(&helpers);
+        let derivation: Vec<_> = tree.rightmost_derivation().iter().map(|form| names.symbols(form)).collect();
+        assert_eq!(derivation, vec![
+            "Block",
+            "{ Stmt Stmt }",
+            "{ Stmt x += ; }",
+            "{ x = x , x ; x += ; }",
+        ]);
+        assert!(tree.pretty(&names).starts_with("Block -> { Stmt* }\n  { @1:1\n  Stmt -> x (= | +=) (x (, x)*)? ;\n"));
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use super::super::grammar::types::*;
+
+type ReduceAction<'a, V> = Box<dyn Fn(&mut Vec<V>) -> V + 'a>;
+
+/// User code run by the parser, like yacc's `$$ = $1 + $3`: every shifted
+/// token gets a value from `term`, and every reduced production a value
+/// computed out of those of its right hand side.
+pub struct SemanticActions<'a, T, V> {
+    term: Box<dyn Fn(&T) -> V + 'a>,
+    empty: Box<dyn Fn() -> V + 'a>,
+    error: Option<Box<dyn Fn() -> V + 'a>>,
+    prods: BTreeMap<Production, ReduceAction<'a, V>>,
+}
+
+impl<'a, T, V> SemanticActions<'a, T, V> {
+    /// Actions giving tokens their value by `term`, and ε the value of
+    /// `empty`
+    pub fn new<F, G>(term: F, empty: G) -> SemanticActions<'a, T, V>
+    where
+        F: Fn(&T) -> V + 'a,
+        G: Fn() -> V + 'a,
+    {
+        SemanticActions {
+            term: Box::new(term),
+            empty: Box::new(empty),
+            error: None,
+            prods: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the value of the `error` terminal, that of ε unless set
+    pub fn on_error<F: Fn() -> V + 'a>(&mut self, error: F) -> &mut SemanticActions<'a, T, V> {
+        self.error = Some(Box::new(error));
+        self
+    }
+
+    /// Sets the action run when reducing `prod`, given the values of the right
+    /// hand side to take from. Productions without one take the value of
+    /// their first symbol, or that of ε if they are empty.
+    pub fn on<F: Fn(&mut Vec<V>) -> V + 'a>(&mut self, prod: Production, action: F) -> &mut SemanticActions<'a, T, V> {
+        self.prods.insert(prod, Box::new(action));
+        self
+    }
+
+    pub fn shift(&self, token: &T) -> V {
+        (self.term)(token)
+    }
+
+    pub fn error(&self) -> V {
+        match &self.error {
+            Some(error) => error(),
+            None => (self.empty)(),
+        }
+    }
+
+    pub fn reduce(&self, prod: &Production, values: &mut Vec<V>) -> V {
+        match self.prods.get(prod) {
+            Some(action) => action(values),
+            None if values.is_empty() => (self.empty)(),
+            None => values.swap_remove(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::lalr_state::LALRState;
+    use crate::grammar::reader::{read_grammar, tokenize};
+
+    fn calculator() -> (LRGrammar<LALRState>, Vec<Production>, SymbolNames) {
+        let GrammarSpec { productions, names, precedence, .. } = read_grammar(r#"
+            %left +
+            %left *
+            E -> E + E
+            E -> E * E
+            E -> "(" E ")"
+            E -> 0
+            E -> 1
+            E -> 2
+            E -> 3
+            E -> 4
+            E -> 5
+            E -> 6
+            E -> 7
+            E -> 8
+            E -> 9
+        "#).unwrap();
+        let parser = LRGrammar::build_with_precedence(1, productions.clone(), &precedence)
+            .expect("Precedence should settle every conflict");
+        (parser, productions, names)
+    }
+
+    #[test]
+    fn evaluate() {
+        let (parser, productions, names) = calculator();
+        let mut actions = SemanticActions::new(|token: &Lexeme| token.value.parse().unwrap_or(0), || 0);
+        actions
+            .on(productions[0].clone(), |v| v[0] + v[2])
+            .on(productions[1].clone(), |v| v[0] * v[2])
+            .on(productions[2].clone(), |v| v[1]);
+        let evaluate = |input| parser.parse_with_actions(&tokenize(input, &names).unwrap(), &actions);
+        assert_eq!(evaluate("2+3*4"), Ok(14));
+        assert_eq!(evaluate("(2+3)*4"), Ok(20));
+        assert_eq!(evaluate("2*3+4*5"), Ok(26));
+        assert_eq!(evaluate("7"), Ok(7));
+        assert!(evaluate("2+").is_err());
+    }
+
+    #[test]
+    fn build_ast() {
+        let (parser, productions, names) = calculator();
+        let mut actions = SemanticActions::new(
+            |token: &Lexeme| format!("{}@{}", token.value, token.start.offset),
+            String::new,
+        );
+        actions
+            .on(productions[0].clone(), |v| format!("({} + {})", v[0], v[2]))
+            .on(productions[1].clone(), |v| format!("({} * {})", v[0], v[2]))
+            .on(productions[2].clone(), |v| v.remove(1));
+        let tokens = tokenize("1+2+3*(4+5)", &names).unwrap();
+        assert_eq!(
+            parser.parse_with_actions(&tokens, &actions).unwrap(),
+            "((1@0 + 2@2) + (3@4 * (4@7 + 5@9)))",
+        );
+    }
+
+    #[test]
+    fn empty_productions() {
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            L -> L x | ε
+        ").unwrap();
+        let parser: LRGrammar<LALRState> = LRGrammar::build(1, productions.clone()).unwrap();
+        let mut actions = SemanticActions::new(|_: &Lexeme| 1, || 0);
+        actions.on(productions[0].clone(), |v| v[0] + v[1]);
+        let count = |input| parser.parse_with_actions(&tokenize(input, &names).unwrap(), &actions);
+        assert_eq!(count(""), Ok(0));
+        assert_eq!(count("xxx"), Ok(3));
+    }
+}
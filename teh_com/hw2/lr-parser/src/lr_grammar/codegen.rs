@@ -0,0 +1,178 @@
+use std::fmt::Write;
+
+use super::types::*;
+use super::super::grammar::types::*;
+
+/// The driver of the generated parser, following its tables
+const DRIVER: &str = r#"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// Index of the token the parser stopped at, the length of the input at its end
+    pub index: usize,
+    /// Terminals the parser could have gone on with
+    pub expected: Vec<usize>,
+}
+
+fn action(state: usize, term: usize) -> Option<Action> {
+    let row = ACTION[state];
+    row.binary_search_by_key(&term, |&(term, _)| term).ok().map(|found| row[found].1)
+}
+
+fn goto(state: usize, nonterm: usize) -> usize {
+    let row = GOTO[state];
+    let found = row.binary_search_by_key(&nonterm, |&(nonterm, _)| nonterm).expect("Missing goto");
+    row[found].1
+}
+
+/// Parses `input`, where `term` gives the terminal of a token. Every token
+/// gets a value from `shift`, and every production reduced one from `reduce`
+/// out of its index and the values of its right hand side. Returns the value
+/// of the start symbol.
+pub fn parse<T, V>(
+    input: &[T],
+    term: impl Fn(&T) -> usize,
+    mut shift: impl FnMut(&T) -> V,
+    mut reduce: impl FnMut(usize, Vec<V>) -> V,
+) -> Result<V, SyntaxError> {
+    let mut states = vec![START_STATE];
+    let mut values = Vec::new();
+    let mut index = 0;
+    loop {
+        let state = *states.last().unwrap();
+        let next = input.get(index).map_or(0, &term);
+        match action(state, next) {
+            Some(Action::Shift(to)) => {
+                states.push(to);
+                values.push(shift(&input[index]));
+                index += 1;
+            }
+            Some(Action::Reduce(prod)) => {
+                let (nonterm, len) = PRODUCTIONS[prod];
+                states.truncate(states.len() - len);
+                let children = values.split_off(values.len() - len);
+                values.push(reduce(prod, children));
+                states.push(goto(*states.last().unwrap(), nonterm));
+            }
+            Some(Action::Accept) => return Ok(values.pop().unwrap()),
+            None => {
+                let expected = ACTION[state].iter().map(|&(term, _)| term).filter(|&term| term != 1).collect();
+                return Err(SyntaxError { index, expected });
+            }
+        }
+    }
+}
+"#;
+
+impl ParseTable {
+    /// Writes a Rust module holding the tables and a driver for them, so that
+    /// the parser can be shipped without building it again. Terminals are
+    /// numbered as in `names`, and productions as they were given to
+    /// `LRGrammar::build`. The generated parser does not recover from errors.
+    pub fn generate_rust(&self, names: &SymbolNames) -> String {
+        let mut result = String::new();
+        result.push_str("// Parser generated by lr-parser, do not edit.\n\n");
+        result.push_str("/// Names of the terminals, `$` (the end of the input) and `error` first\n");
+        writeln!(result, "pub const TERMS: &[&str] = &{:?};", names.terms).unwrap();
+        result.push_str("/// Names of the nonterminals, the made up start symbol first\n");
+        writeln!(result, "pub const NONTERMS: &[&str] = &{:?};", names.nonterms).unwrap();
+        result.push_str("/// Left hand side and length of the right hand side of each production\n");
+        result.push_str("pub const PRODUCTIONS: &[(usize, usize)] = &[\n");
+        for prod in &self.productions[1..] {
+            writeln!(result, "    ({}, {}), // {}", prod.s, prod.b.len(), names.production(prod)).unwrap();
+        }
+        result.push_str("];\n\n");
+
+        result.push_str("#[derive(Debug, Clone, Copy, PartialEq, Eq)]\n");
+        result.push_str("pub enum Action {\n    Shift(usize),\n    Reduce(usize),\n    Accept,\n}\n\n");
+        // The parser accepts on shifting $, so the state it would enter is
+        // left out, and the others numbered without it
+        let mut accepted = vec![false; self.state_count()];
+        for state in 0..self.state_count() {
+            if let Some(Action::Shift(to)) = self.action(state, END_TERM) {
+                accepted[to] = true;
+            }
+        }
+        let states: Vec<StateId> = (0..self.state_count()).filter(|&state| !accepted[state]).collect();
+        let mut number = vec![0; self.state_count()];
+        for (index, &state) in states.iter().enumerate() {
+            number[state] = index;
+        }
+
+        writeln!(result, "pub const START_STATE: usize = {};", number[self.init_state]).unwrap();
+        result.push_str("/// The moves of each state, by terminal\n");
+        result.push_str("pub const ACTION: &[&[(usize, Action)]] = &[\n");
+        for &state in &states {
+            let moves: Vec<_> = self.actions(state)
+                .map(|(term, curr_move)| {
+                    let curr_move = match curr_move {
+                        Action::Shift(..) if term == END_TERM => "Action::Accept".to_string(),
+                        Action::Shift(to) => format!("Action::Shift({})", number[to]),
+                        Action::Reduce(prod) => format!("Action::Reduce({})", prod - 1),
+                    };
+                    format!("({}, {})", term, curr_move)
+                })
+                .collect();
+            writeln!(result, "    &[{}],", moves.join(", ")).unwrap();
+        }
+        result.push_str("];\n");
+        result.push_str("/// The state reached from each state after reducing to a nonterminal\n");
+        result.push_str("pub const GOTO: &[&[(usize, usize)]] = &[\n");
+        for &state in &states {
+            let gotos: Vec<_> = (0..self.nonterm_count)
+                .filter_map(|nonterm| self.goto(state, nonterm).map(|to| format!("({}, {})", nonterm, number[to])))
+                .collect();
+            writeln!(result, "    &[{}],", gotos.join(", ")).unwrap();
+        }
+        result.push_str("];\n");
+        result.push_str(DRIVER);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::lalr_state::LALRState;
+    use crate::grammar::reader::read_grammar;
+    use crate::lexer::Lexer;
+
+    const CALCULATOR: &str = "
+        n = [0-9]+
+        %skip [ ]+
+        %left +
+        %left *
+        E -> E + E | E * E | n
+    ";
+
+    #[allow(dead_code)]
+    mod calculator {
+        include!("codegen/calculator.rs");
+    }
+
+    #[test]
+    fn generated_parser() {
+        let spec = read_grammar(CALCULATOR).unwrap();
+        let parser: LRGrammar<LALRState> = LRGrammar::build_with_precedence(1, spec.productions.clone(), &spec.precedence)
+            .expect("Precedence should settle every conflict");
+        assert_eq!(parser.table().generate_rust(&spec.names), include_str!("codegen/calculator.rs"));
+
+        let lexer = Lexer::build(&spec).unwrap();
+        let evaluate = |input| {
+            calculator::parse(
+                &lexer.tokenize(input).unwrap(),
+                |token| token.term,
+                |token| token.value.parse::<i64>().unwrap_or(0),
+                |prod, v| match prod {
+                    0 => v[0] + v[2],
+                    1 => v[0] * v[2],
+                    _ => v[0],
+                },
+            )
+        };
+        assert_eq!(evaluate("2 + 3 * 4"), Ok(14));
+        assert_eq!(evaluate("2 * 3 + 4 * 5"), Ok(26));
+        let n = spec.names.find_term("n").unwrap();
+        assert_eq!(evaluate("2 + * 3"), Err(calculator::SyntaxError { index: 2, expected: vec![n] }));
+        assert_eq!(calculator::TERMS[n], "n");
+    }
+}
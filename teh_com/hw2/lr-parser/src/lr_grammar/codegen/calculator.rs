@@ -0,0 +1,98 @@
+// Parser generated by lr-parser, do not edit.
+
+/// Names of the terminals, `$` (the end of the input) and `error` first
+pub const TERMS: &[&str] = &["$", "error", "n", "+", "*"];
+/// Names of the nonterminals, the made up start symbol first
+pub const NONTERMS: &[&str] = &["S'", "E"];
+/// Left hand side and length of the right hand side of each production
+pub const PRODUCTIONS: &[(usize, usize)] = &[
+    (1, 3), // E -> E + E
+    (1, 3), // E -> E * E
+    (1, 1), // E -> n
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Shift(usize),
+    Reduce(usize),
+    Accept,
+}
+
+pub const START_STATE: usize = 0;
+/// The moves of each state, by terminal
+pub const ACTION: &[&[(usize, Action)]] = &[
+    &[(2, Action::Shift(1))],
+    &[(0, Action::Reduce(2)), (3, Action::Reduce(2)), (4, Action::Reduce(2))],
+    &[(0, Action::Accept), (3, Action::Shift(3)), (4, Action::Shift(4))],
+    &[(2, Action::Shift(1))],
+    &[(2, Action::Shift(1))],
+    &[(0, Action::Reduce(0)), (3, Action::Reduce(0)), (4, Action::Shift(4))],
+    &[(0, Action::Reduce(1)), (3, Action::Reduce(1)), (4, Action::Reduce(1))],
+];
+/// The state reached from each state after reducing to a nonterminal
+pub const GOTO: &[&[(usize, usize)]] = &[
+    &[(1, 2)],
+    &[],
+    &[],
+    &[(1, 5)],
+    &[(1, 6)],
+    &[],
+    &[],
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// Index of the token the parser stopped at, the length of the input at its end
+    pub index: usize,
+    /// Terminals the parser could have gone on with
+    pub expected: Vec<usize>,
+}
+
+fn action(state: usize, term: usize) -> Option<Action> {
+    let row = ACTION[state];
+    row.binary_search_by_key(&term, |&(term, _)| term).ok().map(|found| row[found].1)
+}
+
+fn goto(state: usize, nonterm: usize) -> usize {
+    let row = GOTO[state];
+    let found = row.binary_search_by_key(&nonterm, |&(nonterm, _)| nonterm).expect("Missing goto");
+    row[found].1
+}
+
+/// Parses `input`, where `term` gives the terminal of a token. Every token
+/// gets a value from `shift`, and every production reduced one from `reduce`
+/// out of its index and the values of its right hand side. Returns the value
+/// of the start symbol.
+pub fn parse<T, V>(
+    input: &[T],
+    term: impl Fn(&T) -> usize,
+    mut shift: impl FnMut(&T) -> V,
+    mut reduce: impl FnMut(usize, Vec<V>) -> V,
+) -> Result<V, SyntaxError> {
+    let mut states = vec![START_STATE];
+    let mut values = Vec::new();
+    let mut index = 0;
+    loop {
+        let state = *states.last().unwrap();
+        let next = input.get(index).map_or(0, &term);
+        match action(state, next) {
+            Some(Action::Shift(to)) => {
+                states.push(to);
+                values.push(shift(&input[index]));
+                index += 1;
+            }
+            Some(Action::Reduce(prod)) => {
+                let (nonterm, len) = PRODUCTIONS[prod];
+                states.truncate(states.len() - len);
+                let children = values.split_off(values.len() - len);
+                values.push(reduce(prod, children));
+                states.push(goto(*states.last().unwrap(), nonterm));
+            }
+            Some(Action::Accept) => return Ok(values.pop().unwrap()),
+            None => {
+                let expected = ACTION[state].iter().map(|&(term, _)| term).filter(|&term| term != 1).collect();
+                return Err(SyntaxError { index, expected });
+            }
+        }
+    }
+}
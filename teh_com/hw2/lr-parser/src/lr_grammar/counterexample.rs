@@ -0,0 +1,378 @@
+use std::cell::Cell;
+use std::collections::{BTreeMap, VecDeque};
+
+use super::types::*;
+use super::super::grammar::types::*;
+
+/// Longest sentence tried when looking for an ambiguity
+const MAX_SENTENCE_LEN: usize = 10;
+/// Number of derivation steps tried in all, shared by the conflicts of a
+/// grammar so that a grammar with many of them still fails quickly
+const SEARCH_BUDGET: usize = 400_000;
+
+/// A concrete input leading to a conflict
+#[derive(Debug, Clone)]
+pub struct Counterexample {
+    /// Shortest viable prefix reaching the conflicting state
+    pub prefix: Vec<Symbol>,
+    /// The shortest input that reduces to `prefix`, if every nonterminal in it
+    /// derives some terminal string
+    pub input: Option<Vec<Term>>,
+    pub lookahead: Term,
+    pub ambiguity: Option<Ambiguity>,
+}
+
+/// A sentence with two different leftmost derivations
+#[derive(Debug, Clone)]
+pub struct Ambiguity {
+    pub sentence: Vec<Term>,
+    pub derivations: (Vec<Production>, Vec<Production>),
+}
+
+pub struct CounterexampleFinder<'a, S: LRState> {
+    grammar: &'a Grammar,
+    prefixes: BTreeMap<S, Vec<Symbol>>,
+    min_yield: BTreeMap<Nonterm, Vec<Term>>,
+    /// Derivation steps left for `find_ambiguity`
+    budget: Cell<usize>,
+}
+
+impl<'a, S: LRState> CounterexampleFinder<'a, S> {
+    pub fn new(init_state: &S, grammar: &'a Grammar) -> CounterexampleFinder<'a, S> {
+        // Shortest viable prefixes, by a BFS over the automaton
+        let symbols = grammar.symbols();
+        let mut prefixes = BTreeMap::new();
+        let mut q = VecDeque::new();
+        prefixes.insert(init_state.clone(), vec![]);
+        q.push_back(init_state.clone());
+        while let Some(first) = q.pop_front() {
+            for &symbol in &symbols {
+                let new_state = first.go_to(symbol, grammar);
+                if new_state.items().is_empty() || prefixes.contains_key(&new_state) {
+                    continue;
+                }
+                let mut prefix = prefixes[&first].clone();
+                prefix.push(symbol);
+                prefixes.insert(new_state.clone(), prefix);
+                q.push_back(new_state);
+            }
+        }
+
+        // Shortest terminal string derived by each nonterminal
+        let mut min_yield: BTreeMap<Nonterm, Vec<Term>> = BTreeMap::new();
+        loop {
+            let mut change = false;
+            for prod in &grammar.productions {
+                let mut curr_yield = Vec::new();
+                let mut productive = true;
+                for &sym in &prod.b {
+                    match sym {
+                        Symbol::Term(t) => curr_yield.push(t),
+                        Symbol::Nonterm(t) => match min_yield.get(&t) {
+                            Some(sub_yield) => curr_yield.extend(sub_yield),
+                            None => {
+                                productive = false;
+                                break;
+                            }
+                        },
+                    }
+                }
+                if !productive {
+                    continue;
+                }
+                let shorter = match min_yield.get(&prod.s) {
+                    Some(prev_yield) => curr_yield.len() < prev_yield.len(),
+                    None => true,
+                };
+                if shorter {
+                    min_yield.insert(prod.s, curr_yield);
+                    change = true;
+                }
+            }
+            if !change {
+                break;
+            }
+        }
+
+        CounterexampleFinder { grammar, prefixes, min_yield, budget: Cell::new(SEARCH_BUDGET) }
+    }
+
+    pub fn find(&self, state: &S, conflict: &Conflict) -> Counterexample {
+        let prefix = self.prefixes
+            .get(state)
+            .cloned()
+            .expect("Every state is reachable from the initial one");
+        let input = self.expand(&prefix);
+        // The two derivations should each take one side of the conflict
+        let relevant = match conflict.kind {
+            ConflictKind::ShiftReduce => (
+                conflict.reductions.clone(),
+                conflict.shifts.iter().map(|item| item.prod.clone()).collect(),
+            ),
+            ConflictKind::ReduceReduce => (
+                conflict.reductions[..1].to_vec(),
+                conflict.reductions[1..].to_vec(),
+            ),
+        };
+        let ambiguity = self.find_ambiguity(&relevant);
+        Counterexample {
+            prefix,
+            input,
+            lookahead: conflict.term,
+            ambiguity,
+        }
+    }
+
+    fn expand(&self, symbols: &[Symbol]) -> Option<Vec<Term>> {
+        let mut result = Vec::new();
+        for &sym in symbols {
+            match sym {
+                Symbol::Term(t) => result.push(t),
+                Symbol::Nonterm(t) => result.extend(self.min_yield.get(&t)?),
+            }
+        }
+        Some(result)
+    }
+
+    fn min_len(&self, sym: Symbol) -> Option<usize> {
+        match sym {
+            Symbol::Term(..) => Some(1),
+            Symbol::Nonterm(t) => self.min_yield.get(&t).map(|min_yield| min_yield.len()),
+        }
+    }
+
+    /// Looks for the shortest sentence with two leftmost derivations, one using
+    /// a production from each side of `relevant`, while the budget lasts
+    fn find_ambiguity(&self, relevant: &(Vec<Production>, Vec<Production>)) -> Option<Ambiguity> {
+        for len in 1..=MAX_SENTENCE_LEN {
+            if self.budget.get() == 0 {
+                break;
+            }
+            let mut search = AmbiguitySearch {
+                finder: self,
+                relevant,
+                len,
+                budget: self.budget.get(),
+                seen: BTreeMap::new(),
+                derivation: Vec::new(),
+            };
+            let rest = vec![Symbol::Nonterm(self.grammar.init_nonterm)];
+            let ambiguity = search.search(&mut Vec::new(), rest);
+            self.budget.set(search.budget);
+            if ambiguity.is_some() {
+                return ambiguity;
+            }
+        }
+        None
+    }
+}
+
+struct AmbiguitySearch<'a, 'b, S: LRState> {
+    finder: &'b CounterexampleFinder<'a, S>,
+    relevant: &'b (Vec<Production>, Vec<Production>),
+    len: usize,
+    budget: usize,
+    seen: BTreeMap<Vec<Term>, Vec<Vec<usize>>>,
+    derivation: Vec<usize>,
+}
+
+impl<'a, 'b, S: LRState> AmbiguitySearch<'a, 'b, S> {
+    /// Expands the leftmost nonterminal of `rest`, `done` being the terminals
+    /// derived so far
+    fn search(&mut self, done: &mut Vec<Term>, rest: Vec<Symbol>) -> Option<Ambiguity> {
+        let finder = self.finder;
+        let productions = &finder.grammar.productions;
+        let leading_terms = rest
+            .iter()
+            .take_while(|sym| matches!(sym, Symbol::Term(..)))
+            .count();
+        let done_len = done.len();
+        for &sym in &rest[..leading_terms] {
+            if let Symbol::Term(t) = sym {
+                done.push(t);
+            }
+        }
+        let result = if leading_terms == rest.len() {
+            if done.len() == self.len {
+                self.record(done.clone())
+            } else {
+                None
+            }
+        } else {
+            let mut result = None;
+            if let Symbol::Nonterm(t) = rest[leading_terms] {
+                for (index, prod) in productions.iter().enumerate() {
+                    if prod.s != t || self.budget == 0 || self.derivation.len() > 3 * self.len + 5 {
+                        continue;
+                    }
+                    self.budget -= 1;
+                    let mut new_rest = prod.b.clone();
+                    new_rest.extend(&rest[(leading_terms + 1)..]);
+                    let min_len = new_rest
+                        .iter()
+                        .map(|&sym| self.finder.min_len(sym))
+                        .sum::<Option<usize>>();
+                    match min_len {
+                        Some(min_len) if done.len() + min_len <= self.len => {}
+                        _ => continue,
+                    }
+                    self.derivation.push(index);
+                    result = self.search(done, new_rest);
+                    self.derivation.pop();
+                    if result.is_some() {
+                        break;
+                    }
+                }
+            }
+            result
+        };
+        done.truncate(done_len);
+        result
+    }
+
+    fn record(&mut self, sentence: Vec<Term>) -> Option<Ambiguity> {
+        let productions = &self.finder.grammar.productions;
+        let (first_side, second_side) = self.relevant;
+        let uses = |derivation: &Vec<usize>, side: &Vec<Production>| {
+            derivation.iter().any(|&index| side.contains(&productions[index]))
+        };
+        let derivations = self.seen.entry(sentence.clone()).or_default();
+        // Comparing with the other derivations costs like expanding
+        self.budget = self.budget.saturating_sub(derivations.len());
+        for other in derivations.iter() {
+            let curr = &self.derivation;
+            if (uses(other, first_side) && uses(curr, second_side))
+                || (uses(other, second_side) && uses(curr, first_side)) {
+                let to_prods = |derivation: &Vec<usize>| derivation
+                    .iter()
+                    .map(|&index| productions[index].clone())
+                    .collect();
+                return Some(Ambiguity {
+                    sentence,
+                    derivations: (to_prods(other), to_prods(&self.derivation)),
+                });
+            }
+        }
+        derivations.push(self.derivation.clone());
+        None
+    }
+}
+
+impl Counterexample {
+    pub fn describe(&self, names: &SymbolNames) -> String {
+        let lookahead = names.symbol(Symbol::Term(self.lookahead));
+        let mut result = format!("  viable prefix: {} . {}\n", names.symbols(&self.prefix), lookahead);
+        if let Some(input) = &self.input {
+            result.push_str(&format!("  example input: {} . {}\n", terms_to_string(input, names), lookahead));
+        }
+        if let Some(ambiguity) = &self.ambiguity {
+            result.push_str(&format!(
+                "  ambiguous sentence: {}\n",
+                terms_to_string(&ambiguity.sentence, names),
+            ));
+            let (first, second) = &ambiguity.derivations;
+            result.push_str(&format!("    first derivation:  {}\n", bracketed(first, names)));
+            result.push_str(&format!("    second derivation: {}\n", bracketed(second, names)));
+        }
+        result
+    }
+}
+
+fn terms_to_string(terms: &[Term], names: &SymbolNames) -> String {
+    terms.iter().map(|&t| names.symbol(Symbol::Term(t))).collect::<Vec<_>>().join(" ")
+}
+
+/// Shows the tree of a leftmost derivation, with every node of more than one
+/// symbol (except the root) between parentheses
+pub fn bracketed(derivation: &[Production], names: &SymbolNames) -> String {
+    fn go(derivation: &[Production], pos: &mut usize, names: &SymbolNames, top: bool) -> String {
+        let prod = &derivation[*pos];
+        *pos += 1;
+        let parts: Vec<String> = prod.b
+            .iter()
+            .map(|&sym| match sym {
+                Symbol::Term(..) => names.symbol(sym),
+                Symbol::Nonterm(..) => go(derivation, pos, names, false),
+            })
+            .filter(|part| !part.is_empty())
+            .collect();
+        if parts.len() > 1 && !top {
+            format!("({})", parts.join(" "))
+        } else {
+            parts.join(" ")
+        }
+    }
+    go(derivation, &mut 0, names, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::{canonical_lr_state::CanonicalLRState, lalr_state::LALRState};
+    use crate::grammar::reader::read_grammar;
+
+    #[test]
+    fn ambiguous_sum() {
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            E -> E + E
+            E -> n
+        ").unwrap();
+        let conflicts = LRGrammar::<CanonicalLRState>::build(1, productions.clone())
+            .err()
+            .expect("Should not be LR(1)")
+            .conflicts;
+        assert_eq!(conflicts.len(), 1);
+        let counterexample = conflicts[0].counterexample.as_ref().unwrap();
+        assert_eq!(counterexample.prefix, productions[0].b);
+        assert_eq!(terms_to_string(counterexample.input.as_ref().unwrap(), &names), "n + n");
+        assert_eq!(names.symbol(Symbol::Term(counterexample.lookahead)), "+");
+
+        let ambiguity = counterexample.ambiguity.as_ref().expect("The grammar is ambiguous");
+        assert_eq!(terms_to_string(&ambiguity.sentence, &names), "n + n + n");
+        let mut derivations = vec![
+            bracketed(&ambiguity.derivations.0, &names),
+            bracketed(&ambiguity.derivations.1, &names),
+        ];
+        derivations.sort();
+        assert_eq!(derivations, vec!["(n + n) + n", "n + (n + n)"]);
+    }
+
+    #[test]
+    fn unambiguous_conflict() {
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            S -> a A d
+            S -> b B d
+            S -> a B e
+            S -> b A e
+            A -> c
+            B -> c
+        ").unwrap();
+        let conflicts = LRGrammar::<LALRState>::build(1, productions)
+            .err()
+            .expect("Should not be LALR(1)")
+            .conflicts;
+        assert!(!conflicts.is_empty());
+        for conflict in &conflicts {
+            let counterexample = conflict.counterexample.as_ref().unwrap();
+            assert_eq!(counterexample.prefix.len(), 2);
+            assert_eq!(counterexample.input.as_ref().unwrap()[1], names.find_term("c").unwrap());
+            assert!(counterexample.ambiguity.is_none());
+        }
+    }
+
+    #[test]
+    fn many_conflicts() {
+        let GrammarSpec { productions, .. } = read_grammar(r#"
+            S -> S S | a | "(" S ")" | ε
+        "#).unwrap();
+        let conflicts = LRGrammar::<LALRState>::build(1, productions)
+            .err()
+            .expect("Should not be LALR(1)")
+            .conflicts;
+        assert!(conflicts.len() > 10);
+        // The search budget is shared, but every conflict gets its prefix
+        assert!(conflicts.iter().all(|conflict| conflict.counterexample.as_ref().unwrap().input.is_some()));
+        assert!(conflicts[0].counterexample.as_ref().unwrap().ambiguity.is_some());
+    }
+}
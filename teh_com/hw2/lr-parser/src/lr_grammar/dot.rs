@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::{automaton, is_accepted};
+use super::types::*;
+use super::super::grammar::types::*;
+
+/// Fill color of the states with conflicts
+const CONFLICT_COLOR: &str = "#ffc0c0";
+
+/// Quotes `text` for a DOT label, ending every line with `\l` so that the
+/// items are left aligned
+fn label(text: &str) -> String {
+    let mut result = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '\n' => result.push_str("\\l"),
+            _ => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// Writes the automaton of `productions`, with states of type `S`, as a
+/// Graphviz DOT graph. Every state shows its items, and the states with a
+/// conflict precedence does not settle are filled in red, with the
+/// terminals they conflict on. As in `report`, the state entered by
+/// shifting `$` is left out.
+pub fn automaton_dot<S: LRState>(
+    init_nonterm: Nonterm,
+    productions: Vec<Production>,
+    precedence: &Precedence,
+    names: &SymbolNames,
+) -> String {
+    let (grammar, _, mut states, table) = automaton::<S>(init_nonterm, productions, precedence);
+    // Numbered last, so the others keep their numbers
+    states.retain(|state| !is_accepted(state));
+    let states_index: BTreeMap<&S, StateId> = states.iter().enumerate().map(|(i, state)| (state, i)).collect();
+
+    let mut result = String::new();
+    result.push_str("digraph automaton {\n");
+    result.push_str("    rankdir=LR;\n");
+    result.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+    for (index, state) in states.iter().enumerate() {
+        let mut text = format!("{}\n", index);
+        for item in state.items() {
+            writeln!(text, "{}", item.describe(names)).unwrap();
+        }
+        let conflicts: Vec<String> = (0..table.term_count)
+            .filter(|&term| table.actions(index, term).len() > 1)
+            .map(|term| names.symbol(Symbol::Term(term)))
+            .collect();
+        let fill = match conflicts.is_empty() {
+            true => String::new(),
+            false => {
+                writeln!(text, "conflicts on {}", conflicts.join(" ")).unwrap();
+                format!(", style=filled, fillcolor=\"{}\"", CONFLICT_COLOR)
+            }
+        };
+        let mut attributes = format!("label={}{}", label(&text), fill);
+        if index == table.init_state {
+            attributes.push_str(", peripheries=2");
+        }
+        writeln!(result, "    {} [{}];", index, attributes).unwrap();
+    }
+    for (index, state) in states.iter().enumerate() {
+        for &sym in &grammar.all_symbols {
+            let to = state.go_to(sym, &grammar);
+            if to.items().is_empty() || is_accepted(&to) {
+                continue;
+            }
+            writeln!(result, "    {} -> {} [label={}];", index, states_index[&to], label(&names.symbol(sym))).unwrap();
+        }
+    }
+    result.push_str("}\n");
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lr_grammar::states::canonical_lr_state::CanonicalLRState;
+    use crate::lr_grammar::states::lr0_state::LR0State;
+    use crate::grammar::reader::read_grammar;
+
+    #[test]
+    fn dot_graph() {
+        let GrammarSpec { productions, names, precedence, .. } = read_grammar(r#"
+            S -> "(" S ")" | x
+        "#).unwrap();
+        let dot = automaton_dot::<CanonicalLRState>(1, productions, &precedence, &names);
+        assert!(dot.starts_with("digraph automaton {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains(r#"    0 [label="0\lS' -> . S $, [$]\lS -> . ( S ), [$]\lS -> . x, [$]\l", peripheries=2];"#));
+        assert!(dot.contains(r#" [label="("];"#));
+        assert!(dot.contains(r#"S -> x ., [)]"#));
+        assert!(!dot.contains("fillcolor"));
+        // Shifting $ accepts
+        assert!(!dot.contains("S' -> S $ ."));
+        assert!(!dot.contains(r#"[label="$"];"#));
+
+        let GrammarSpec { productions, names, precedence, .. } = read_grammar(r#"
+            E -> E + E | n | "\"" E "\""
+        "#).unwrap();
+        let dot = automaton_dot::<LR0State>(1, productions, &precedence, &names);
+        // Shift/reduce on + once E + E is read
+        assert_eq!(dot.matches("fillcolor").count(), 1);
+        assert!(dot.contains(r#"\lE -> E . + E\lE -> E + E .\lconflicts on +\l", style=filled"#));
+        assert!(dot.contains(r#"[label="\""];"#));
+    }
+
+    #[test]
+    fn display_states() {
+        let GrammarSpec { productions, .. } = read_grammar("S -> x").unwrap();
+        let mut productions = productions;
+        productions.insert(0, Production { s: 0, b: vec![Symbol::Nonterm(1), Symbol::Term(END_TERM)] });
+        let grammar = Grammar::build(1, productions);
+        let state = CanonicalLRState::initial_state(grammar.productions[0].clone(), &grammar);
+        assert_eq!(format!("{}", state), "<0> -> . <1> <t0>, [<t0>]\n<1> -> . <t2>, [<t0>]\n");
+        assert!(format!("{:?}", state).starts_with("CanonicalLRState(["));
+        let state = LR0State::initial_state(grammar.productions[0].clone(), &grammar);
+        assert_eq!(format!("{}", state), "<0> -> . <1> <t0>\n<1> -> . <t2>\n");
+    }
+}
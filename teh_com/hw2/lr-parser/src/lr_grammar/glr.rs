@@ -0,0 +1,317 @@
+use std::collections::BTreeMap;
+
+use super::automaton;
+use super::types::*;
+use super::super::grammar::types::*;
+use super::super::grammar::position_at;
+
+/// A node of the graph-structured stack: a state pushed at some point of the
+/// input, with edges to the nodes below it, labelled by the forest node of
+/// the symbol in between
+struct StackNode {
+    state: StateId,
+    level: usize,
+    edges: Vec<(usize, usize)>,
+}
+
+/// Builds the forest, sharing a node between every derivation of a symbol
+/// over the same tokens
+struct ForestBuilder<'a, T> {
+    input: &'a [T],
+    nodes: Vec<ForestNode>,
+    index: BTreeMap<(Symbol, usize, usize), usize>,
+}
+
+impl<'a, T: Token> ForestBuilder<'a, T> {
+    fn node(&mut self, symbol: Symbol, start: usize, end: usize) -> usize {
+        let (input, nodes) = (self.input, &mut self.nodes);
+        *self.index.entry((symbol, start, end)).or_insert_with(|| {
+            nodes.push(ForestNode {
+                symbol,
+                start,
+                end,
+                position: position_at(input, start),
+                alternatives: Vec::new(),
+            });
+            nodes.len() - 1
+        })
+    }
+
+    /// Adds a derivation to `node`, returning whether it is new
+    fn pack(&mut self, node: usize, prod: &Production, children: Vec<usize>) -> bool {
+        let alternatives = &mut self.nodes[node].alternatives;
+        if alternatives.iter().any(|(other, other_children)| other == prod && *other_children == children) {
+            return false;
+        }
+        alternatives.push((prod.clone(), children));
+        true
+    }
+
+    /// The forest of the nodes reachable from `root`, numbered again
+    fn finish(self, root: usize) -> ParseForest {
+        let mut new_index = vec![None; self.nodes.len()];
+        let mut order = vec![root];
+        new_index[root] = Some(0);
+        let mut next = 0;
+        while let Some(&node) = order.get(next) {
+            next += 1;
+            for (_, children) in &self.nodes[node].alternatives {
+                for &child in children {
+                    if new_index[child].is_none() {
+                        new_index[child] = Some(order.len());
+                        order.push(child);
+                    }
+                }
+            }
+        }
+        let nodes = order
+            .iter()
+            .map(|&node| {
+                let mut node = self.nodes[node].clone();
+                for (_, children) in &mut node.alternatives {
+                    for child in children {
+                        *child = new_index[*child].unwrap();
+                    }
+                }
+                node
+            })
+            .collect();
+        ParseForest { nodes, root: 0 }
+    }
+}
+
+/// The nodes `len` edges below `node`, each with the forest nodes of the
+/// edges followed, from the bottom up
+fn paths(stack: &[StackNode], node: usize, len: usize) -> Vec<(usize, Vec<usize>)> {
+    if len == 0 {
+        return vec![(node, Vec::new())];
+    }
+    let mut result = Vec::new();
+    for &(below, forest_node) in &stack[node].edges {
+        for (bottom, mut children) in paths(stack, below, len - 1) {
+            children.push(forest_node);
+            result.push((bottom, children));
+        }
+    }
+    result
+}
+
+impl GLRTable {
+    /// Builds the table of `productions` out of states of type `S`. Unlike
+    /// `LRGrammar::build_with_precedence`, the conflicts precedence does not
+    /// settle are kept, with all their moves.
+    pub fn build<S: LRState>(init_nonterm: Nonterm, productions: Vec<Production>, precedence: &Precedence) -> GLRTable {
+        automaton::<S>(init_nonterm, productions, precedence).3
+    }
+
+    pub fn state_count(&self) -> usize {
+        self.action.len() / self.term_count
+    }
+
+    pub fn actions(&self, state: StateId, term: Term) -> &[Action] {
+        if term >= self.term_count {
+            return &[];
+        }
+        &self.action[state * self.term_count + term]
+    }
+
+    pub fn goto(&self, state: StateId, nonterm: Nonterm) -> Option<StateId> {
+        self.goto[state * self.nonterm_count + nonterm]
+    }
+
+    /// Whether some state has more than one move on a terminal, so that an
+    /// LR parser could not use the table
+    pub fn has_conflicts(&self) -> bool {
+        self.action.iter().any(|actions| actions.len() > 1)
+    }
+
+    /// Parses `input` following every move of the conflicts at once, on a
+    /// graph-structured stack where the parsers in the same state share a
+    /// node. Returns all the parses, or the error where the last of them
+    /// failed.
+    ///
+    /// Reductions are done over and over until they add nothing, so that
+    /// empty productions reduced after a node got new edges are not missed.
+    pub fn parse_forest<T: Token>(&self, input: &[T]) -> Result<ParseForest, ParseError> {
+        let init_nonterm = match self.productions[0].b[0] {
+            Symbol::Nonterm(nonterm) => nonterm,
+            Symbol::Term(..) => unreachable!("S' -> S $ starts with the start symbol"),
+        };
+        let mut forest = ForestBuilder { input, nodes: Vec::new(), index: BTreeMap::new() };
+        let mut stack = vec![StackNode { state: self.init_state, level: 0, edges: Vec::new() }];
+        // The nodes of the current level, by state
+        let mut frontier: BTreeMap<StateId, usize> = vec![(self.init_state, 0)].into_iter().collect();
+        for index in 0..=input.len() {
+            let next_input = input.get(index).map_or(END_TERM, |token| token.term());
+            loop {
+                let mut change = false;
+                let nodes: Vec<usize> = frontier.values().cloned().collect();
+                for node in nodes {
+                    for &curr_move in self.actions(stack[node].state, next_input) {
+                        let prod = match curr_move {
+                            Action::Reduce(prod) => &self.productions[prod],
+                            Action::Shift(..) => continue,
+                        };
+                        for (bottom, children) in paths(&stack, node, prod.b.len()) {
+                            let forest_node = forest.node(Symbol::Nonterm(prod.s), stack[bottom].level, index);
+                            change |= forest.pack(forest_node, prod, children);
+                            let state = self.goto(stack[bottom].state, prod.s).expect("Reductions always have a goto");
+                            let top = *frontier.entry(state).or_insert_with(|| {
+                                stack.push(StackNode { state, level: index, edges: Vec::new() });
+                                stack.len() - 1
+                            });
+                            if !stack[top].edges.contains(&(bottom, forest_node)) {
+                                stack[top].edges.push((bottom, forest_node));
+                                change = true;
+                            }
+                        }
+                    }
+                }
+                if !change {
+                    break;
+                }
+            }
+
+            let mut next_frontier = BTreeMap::new();
+            for &node in frontier.values() {
+                for &curr_move in self.actions(stack[node].state, next_input) {
+                    let state = match curr_move {
+                        Action::Shift(state) => state,
+                        Action::Reduce(..) => continue,
+                    };
+                    if next_input == END_TERM {
+                        let root = forest.index[&(Symbol::Nonterm(init_nonterm), 0, index)];
+                        return Ok(forest.finish(root));
+                    }
+                    let leaf = forest.node(Symbol::Term(next_input), index, index + 1);
+                    let top = *next_frontier.entry(state).or_insert_with(|| {
+                        stack.push(StackNode { state, level: index + 1, edges: Vec::new() });
+                        stack.len() - 1
+                    });
+                    stack[top].edges.push((node, leaf));
+                }
+            }
+            if next_frontier.is_empty() {
+                let mut expected: Vec<Term> = frontier
+                    .keys()
+                    .flat_map(|&state| (0..self.term_count).filter(move |&term| !self.actions(state, term).is_empty()))
+                    .filter(|&term| term != ERROR_TERM)
+                    .collect();
+                expected.sort_unstable();
+                expected.dedup();
+                return Err(ParseError {
+                    position: position_at(input, index),
+                    unexpected: input.get(index).map(|token| token.term()),
+                    expected,
+                });
+            }
+            frontier = next_frontier;
+        }
+        unreachable!("The end of the input is either accepted or an error")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::lalr_state::LALRState;
+    use crate::lr_grammar::states::canonical_lr_state::CanonicalLRState;
+    use crate::grammar::reader::{read_grammar, tokenize};
+
+    fn glr(grammar: &str) -> (GLRTable, SymbolNames) {
+        let GrammarSpec { productions, names, precedence, .. } = read_grammar(grammar).unwrap();
+        (GLRTable::build::<LALRState>(1, productions, &precedence), names)
+    }
+
+    fn forest(table: &GLRTable, input: &str, names: &SymbolNames) -> Result<ParseForest, ParseError> {
+        table.parse_forest(&tokenize(input, names).unwrap())
+    }
+
+    #[test]
+    fn ambiguous_expressions() {
+        let (table, names) = glr("E -> E + E | E * E | n");
+        assert!(table.has_conflicts());
+        let count = |input| forest(&table, input, &names).unwrap().count();
+        assert_eq!(count("n"), Some(1));
+        assert_eq!(count("n+n*n"), Some(2));
+        // Catalan numbers
+        assert_eq!(count("n+n+n+n"), Some(5));
+        assert_eq!(count("n+n+n+n+n+n"), Some(42));
+
+        let forest = forest(&table, "n+n+n", &names).unwrap();
+        let trees: Vec<_> = forest
+            .trees()
+            .iter()
+            .map(|tree| names.symbols(&tree.rightmost_derivation()[1]))
+            .collect();
+        assert_eq!(trees.len(), 2);
+        assert!(trees.contains(&"E + E".to_string()));
+        // Both parses share the three n, and E over each of them
+        let n = Symbol::Term(names.find_term("n").unwrap());
+        assert_eq!(forest.nodes.iter().filter(|node| node.symbol == n).count(), 3);
+        assert_eq!(forest.nodes[forest.root].alternatives.len(), 2);
+    }
+
+    #[test]
+    fn prepositional_phrases() {
+        // I saw the man with the telescope
+        let (table, names) = glr("
+            S -> NP VP
+            NP -> n | d n | NP PP
+            VP -> v NP | VP PP
+            PP -> p NP
+        ");
+        let forest = forest(&table, "n v d n p d n", &names).unwrap();
+        assert_eq!(forest.count(), Some(2));
+        let trees = forest.trees();
+        assert_eq!(trees.len(), 2);
+        assert_ne!(trees[0], trees[1]);
+        for tree in &trees {
+            assert_eq!(names.symbols(&tree.leaves().into_iter().map(Symbol::Term).collect::<Vec<_>>()), "n v d n p d n");
+        }
+    }
+
+    #[test]
+    fn deterministic_grammars() {
+        let GrammarSpec { productions, names, .. } = read_grammar(r#"
+            S -> S "(" S ")" | ε
+        "#).unwrap();
+        let table = GLRTable::build::<CanonicalLRState>(1, productions.clone(), &Precedence::default());
+        assert!(!table.has_conflicts());
+        let parser: LRGrammar<CanonicalLRState> = LRGrammar::build(1, productions).unwrap();
+        for input in &["", "()", "(()())()"] {
+            let input = tokenize(input, &names).unwrap();
+            assert_eq!(table.parse_forest(&input).unwrap().trees(), vec![parser.parse_tree(&input).unwrap()]);
+        }
+    }
+
+    #[test]
+    fn empty_productions_and_cycles() {
+        // Hidden left recursion
+        let (table, names) = glr("
+            S -> A S a | a
+            A -> ε
+        ");
+        assert_eq!(forest(&table, "aaa", &names).unwrap().count(), Some(1));
+
+        let (table, names) = glr("
+            S -> S | A a
+            A -> ε | A
+        ");
+        let forest = forest(&table, "a", &names).unwrap();
+        assert_eq!(forest.count(), None);
+        assert_eq!(forest.trees().len(), 1);
+    }
+
+    #[test]
+    fn errors() {
+        let (table, names) = glr("E -> E + E | E * E | n");
+        let error = forest(&table, "n+*n", &names).unwrap_err();
+        assert_eq!(error.position.offset, 2);
+        assert_eq!(error.unexpected, names.find_term("*"));
+        assert_eq!(error.expected, vec![names.find_term("n").unwrap()]);
+        let error = forest(&table, "n+", &names).unwrap_err();
+        assert_eq!(error.unexpected, None);
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::BTreeSet;
+use std::fmt;
 
 use super::types::*;
 use super::super::grammar::types::*;
@@ -10,15 +11,15 @@ pub fn get_first<G: GrammarInfo>(symbols: &[Symbol], info: &G) ->
     let mut nullable = true;
     for symbol in symbols {
         match symbol {
-            &Symbol::Nonterm(t) => {
-                first.extend(info.first(t));
-                if !info.nullable(t) {
+            Symbol::Nonterm(t) => {
+                first.extend(info.first(*t));
+                if !info.nullable(*t) {
                     nullable = false;
                     break;
                 }
             }
-            &Symbol::Term(t) => {
-                first.insert(t);
+            Symbol::Term(t) => {
+                first.insert(*t);
                 nullable = false;
                 break;
             }
@@ -26,3 +27,11 @@ pub fn get_first<G: GrammarInfo>(symbols: &[Symbol], info: &G) ->
     }
     (first.into_iter().collect(), nullable)
 }
+
+/// Writes the items of a state one per line, for its `Display`
+pub fn write_items(items: &[Item], f: &mut fmt::Formatter) -> fmt::Result {
+    for item in items {
+        writeln!(f, "{}", item)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,286 @@
+use std::fmt::Write;
+
+use super::{automaton, is_accepted};
+use super::types::*;
+use super::super::grammar::types::*;
+
+/// How `report` lays out its headings, item sets and tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Columns padded with spaces
+    Text,
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    /// The format called `name`: `text`, `markdown` or `html`
+    pub fn from_name(name: &str) -> Option<ReportFormat> {
+        match name {
+            "text" => Some(ReportFormat::Text),
+            "markdown" | "md" => Some(ReportFormat::Markdown),
+            "html" => Some(ReportFormat::Html),
+            _ => None,
+        }
+    }
+
+    fn heading(self, result: &mut String, level: usize, title: &str) {
+        match self {
+            ReportFormat::Text => {
+                let underline = if level == 1 { '=' } else { '-' };
+                writeln!(result, "{}\n{}\n", title, underline.to_string().repeat(title.chars().count())).unwrap();
+            }
+            ReportFormat::Markdown => writeln!(result, "{} {}\n", "#".repeat(level + 1), title).unwrap(),
+            ReportFormat::Html => writeln!(result, "<h{0}>{1}</h{0}>", level + 1, escape_html(title)).unwrap(),
+        }
+    }
+
+    /// Lines kept as they are, like the items of a state
+    fn lines(self, result: &mut String, lines: &[String]) {
+        match self {
+            ReportFormat::Text => {
+                for line in lines {
+                    writeln!(result, "    {}", line).unwrap();
+                }
+                result.push('\n');
+            }
+            ReportFormat::Markdown => {
+                result.push_str("```\n");
+                for line in lines {
+                    writeln!(result, "{}", line).unwrap();
+                }
+                result.push_str("```\n\n");
+            }
+            ReportFormat::Html => {
+                result.push_str("<pre>\n");
+                for line in lines {
+                    writeln!(result, "{}", escape_html(line)).unwrap();
+                }
+                result.push_str("</pre>\n");
+            }
+        }
+    }
+
+    /// A table whose first row is the header
+    fn table(self, result: &mut String, rows: &[Vec<String>]) {
+        let cell = |text: &str| match self {
+            ReportFormat::Markdown => text.replace('\\', "\\\\").replace('|', "\\|"),
+            _ => text.to_string(),
+        };
+        let rows: Vec<Vec<String>> = rows.iter().map(|row| row.iter().map(|text| cell(text)).collect()).collect();
+        // Markdown needs at least three dashes under each header
+        let least = if self == ReportFormat::Markdown { 3 } else { 0 };
+        let mut widths = vec![least; rows[0].len()];
+        for row in &rows {
+            for (width, text) in widths.iter_mut().zip(row) {
+                *width = (*width).max(text.chars().count());
+            }
+        }
+        let pad = |text: &str, width: usize| format!("{}{}", text, " ".repeat(width - text.chars().count()));
+        match self {
+            ReportFormat::Text => {
+                for (i, row) in rows.iter().enumerate() {
+                    let line: Vec<String> = row.iter().zip(&widths).map(|(text, &width)| pad(text, width)).collect();
+                    writeln!(result, "{}", line.join(" | ").trim_end()).unwrap();
+                    if i == 0 {
+                        let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+                        writeln!(result, "{}", rule.join("-+-")).unwrap();
+                    }
+                }
+                result.push('\n');
+            }
+            ReportFormat::Markdown => {
+                for (i, row) in rows.iter().enumerate() {
+                    let line: Vec<String> = row.iter().zip(&widths).map(|(text, &width)| pad(text, width)).collect();
+                    writeln!(result, "| {} |", line.join(" | ")).unwrap();
+                    if i == 0 {
+                        let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+                        writeln!(result, "|-{}-|", rule.join("-|-")).unwrap();
+                    }
+                }
+                result.push('\n');
+            }
+            ReportFormat::Html => {
+                result.push_str("<table>\n");
+                for (i, row) in rows.iter().enumerate() {
+                    let tag = if i == 0 { "th" } else { "td" };
+                    result.push_str("<tr>");
+                    for text in row {
+                        write!(result, "<{0}>{1}</{0}>", tag, escape_html(text)).unwrap();
+                    }
+                    result.push_str("</tr>\n");
+                }
+                result.push_str("</table>\n");
+            }
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Writes what building the automaton of `productions` computes, the way
+/// textbooks show it: the numbered productions, FIRST, FOLLOW and nullable
+/// for every nonterminal, the items of every state, and the ACTION and GOTO
+/// tables. Conflicting moves share their cell, as in `s4/r2`, and `acc`
+/// stands for shifting `$` in `S' -> S . $`, the state after it being left
+/// out.
+pub fn report<S: LRState>(
+    init_nonterm: Nonterm,
+    productions: Vec<Production>,
+    precedence: &Precedence,
+    names: &SymbolNames,
+    format: ReportFormat,
+) -> String {
+    let (grammar, _, states, table) = automaton::<S>(init_nonterm, productions, precedence);
+    let terms = |terms: Vec<Term>| terms.into_iter().map(|term| names.symbol(Symbol::Term(term))).collect::<Vec<_>>().join(" ");
+    let mut result = String::new();
+
+    format.heading(&mut result, 1, "Productions");
+    let productions: Vec<String> = grammar.productions
+        .iter()
+        .enumerate()
+        .map(|(index, prod)| format!("{}: {}", index, names.production(prod)))
+        .collect();
+    format.lines(&mut result, &productions);
+
+    format.heading(&mut result, 1, "FIRST, FOLLOW and nullable");
+    let mut rows = vec![vec!["".to_string(), "FIRST".to_string(), "FOLLOW".to_string(), "nullable".to_string()]];
+    let mut nonterms: Vec<Nonterm> = grammar.productions.iter().map(|prod| prod.s).collect();
+    nonterms.sort_unstable();
+    nonterms.dedup();
+    for &nonterm in &nonterms {
+        rows.push(vec![
+            names.symbol(Symbol::Nonterm(nonterm)),
+            terms(grammar.first(nonterm)),
+            terms(grammar.follow(nonterm)),
+            if grammar.nullable(nonterm) { "yes" } else { "no" }.to_string(),
+        ]);
+    }
+    format.table(&mut result, &rows);
+
+    // The state entered by shifting $, numbered last, is left out: the
+    // parser accepts instead
+    let shown = states.iter().take_while(|state| !is_accepted(*state)).count();
+    format.heading(&mut result, 1, "States");
+    for (index, state) in states[..shown].iter().enumerate() {
+        format.heading(&mut result, 2, &format!("State {}", index));
+        let items: Vec<String> = state.items().iter().map(|item| item.describe(names)).collect();
+        format.lines(&mut result, &items);
+    }
+
+    format.heading(&mut result, 1, "ACTION and GOTO");
+    let mut columns: Vec<Symbol> = vec![Symbol::Term(END_TERM)];
+    columns.extend(grammar.all_symbols.iter().filter(|&&sym| match sym {
+        Symbol::Term(term) => term != END_TERM,
+        Symbol::Nonterm(nonterm) => nonterm != 0,
+    }));
+    let mut rows = vec![std::iter::once("".to_string()).chain(columns.iter().map(|&sym| names.symbol(sym))).collect::<Vec<_>>()];
+    for index in 0..shown {
+        let mut row = vec![index.to_string()];
+        for &sym in &columns {
+            row.push(match sym {
+                Symbol::Term(term) => table
+                    .actions(index, term)
+                    .iter()
+                    .map(|action| match *action {
+                        // The parser stops on shifting $
+                        Action::Shift(..) if term == END_TERM => "acc".to_string(),
+                        Action::Shift(to) => format!("s{}", to),
+                        Action::Reduce(prod) => format!("r{}", prod),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                Symbol::Nonterm(nonterm) => table.goto[index * table.nonterm_count + nonterm]
+                    .map_or(String::new(), |to| to.to_string()),
+            });
+        }
+        rows.push(row);
+    }
+    format.table(&mut result, &rows);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lr_grammar::states::canonical_lr_state::CanonicalLRState;
+    use crate::lr_grammar::states::lr0_state::LR0State;
+    use crate::lr_grammar::states::slr_state::SLRState;
+    use crate::grammar::reader::read_grammar;
+
+    fn report_of<S: LRState>(grammar: &str, format: ReportFormat) -> String {
+        let GrammarSpec { productions, names, precedence, .. } = read_grammar(grammar).unwrap();
+        report::<S>(1, productions, &precedence, &names, format)
+    }
+
+    #[test]
+    fn text_report() {
+        let report = report_of::<SLRState>(r#"
+            S -> A A
+            A -> a A | b
+        "#, ReportFormat::Text);
+        assert!(report.starts_with("Productions\n===========\n\n    0: S' -> S $\n    1: S -> A A\n    2: A -> a A\n    3: A -> b\n"));
+        assert!(report.contains(concat!(
+            "   | FIRST | FOLLOW | nullable\n",
+            "---+-------+--------+---------\n",
+            "S' | a b   |        | no\n",
+            "S  | a b   | $      | no\n",
+            "A  | a b   | $ a b  | no\n",
+        )));
+        assert!(report.contains("State 0\n-------\n\n    S' -> . S $\n    S -> . A A\n    A -> . a A\n    A -> . b\n"));
+        assert!(report.ends_with(concat!(
+            "ACTION and GOTO\n",
+            "===============\n",
+            "\n",
+            "  | $   | a  | b  | S | A\n",
+            "--+-----+----+----+---+--\n",
+            "0 |     | s4 | s6 | 1 | 2\n",
+            "1 | acc |    |    |   |\n",
+            "2 |     | s4 | s6 |   | 3\n",
+            "3 | r1  |    |    |   |\n",
+            "4 |     | s4 | s6 |   | 5\n",
+            "5 | r2  | r2 | r2 |   |\n",
+            "6 | r3  | r3 | r3 |   |\n",
+            "\n",
+        )));
+    }
+
+    #[test]
+    fn conflicts_and_formats() {
+        let report = report_of::<LR0State>("E -> E + E | n", ReportFormat::Markdown);
+        assert!(report.contains("## States\n\n### State 0\n\n```\nS' -> . E $\nE -> . n\nE -> . E + E\n```\n"));
+        assert!(report.contains(concat!(
+            "|     | $   | +     | n   | E   |\n",
+            "|-----|-----|-------|-----|-----|\n",
+        )));
+        assert!(report.contains("| 1   | acc | s2    |     |     |\n"));
+        assert!(report.contains("| 4   | r1  | s2/r1 | r1  |     |\n"));
+        assert!(!report.contains("r0"));
+
+        let report = report_of::<LR0State>(r#"E -> E "<" E | n"#, ReportFormat::Html);
+        assert!(report.contains("<h2>States</h2>\n<h3>State 0</h3>\n<pre>\nS' -&gt; . E $\n"));
+        assert!(report.contains("<tr><th></th><th>$</th><th>&lt;</th><th>n</th><th>E</th></tr>\n"));
+        assert!(report.contains("<tr><td>4</td><td>r1</td><td>s2/r1</td><td>r1</td><td></td></tr>\n"));
+    }
+
+    #[test]
+    fn textbook_numbering() {
+        let report = report_of::<CanonicalLRState>("
+            S -> A B
+            A -> ε
+            B -> ε
+        ", ReportFormat::Text);
+        assert!(report.ends_with(concat!(
+            "  | $   | S | A | B\n",
+            "--+-----+---+---+--\n",
+            "0 | r2  | 1 | 2 |\n",
+            "1 | acc |   |   |\n",
+            "2 | r3  |   |   | 3\n",
+            "3 | r1  |   |   |\n",
+            "\n",
+        )));
+        assert!(!report.contains("S' -> S $ ."));
+    }
+}
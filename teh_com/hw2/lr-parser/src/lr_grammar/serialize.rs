@@ -0,0 +1,738 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use super::types::*;
+use super::super::grammar::types::*;
+
+/// Bumped whenever the layout of the files changes
+pub const TABLES_VERSION: usize = 1;
+
+const JSON_FORMAT: &str = "lr-parser tables";
+const BINARY_MAGIC: &[u8] = b"LRPT";
+
+fn load_error<T>(message: impl Into<String>) -> Result<T, LoadError> {
+    Err(LoadError { message: message.into() })
+}
+
+/// Splits `t3` into its letter and its number
+fn tagged(text: &str) -> Option<(char, usize)> {
+    let mut chars = text.chars();
+    let tag = chars.next()?;
+    chars.as_str().parse().ok().map(|number| (tag, number))
+}
+
+fn json_string(text: &str) -> String {
+    let mut result = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            c if (c as u32) < 0x20 => write!(result, "\\u{:04x}", c as u32).unwrap(),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn json_strings(texts: &[String]) -> String {
+    let texts: Vec<_> = texts.iter().map(|text| json_string(text)).collect();
+    format!("[{}]", texts.join(", "))
+}
+
+impl ParseTable {
+    /// Writes the tables and the names of the symbols as JSON, a row per
+    /// production and per state. Symbols are written `t3` for terminal 3 and
+    /// `n1` for nonterminal 1, moves `s4` to shift to state 4 and `r2` to
+    /// reduce by production 2.
+    pub fn to_json(&self, names: &SymbolNames) -> String {
+        let mut result = String::from("{\n");
+        writeln!(result, "  \"format\": {},", json_string(JSON_FORMAT)).unwrap();
+        writeln!(result, "  \"version\": {},", TABLES_VERSION).unwrap();
+        writeln!(result, "  \"terms\": {},", json_strings(&names.terms)).unwrap();
+        writeln!(result, "  \"nonterms\": {},", json_strings(&names.nonterms)).unwrap();
+        let productions: Vec<_> = self.productions
+            .iter()
+            .map(|prod| {
+                let b: Vec<_> = prod.b
+                    .iter()
+                    .map(|sym| match sym {
+                        Symbol::Term(term) => format!("\"t{}\"", term),
+                        Symbol::Nonterm(nonterm) => format!("\"n{}\"", nonterm),
+                    })
+                    .collect();
+                format!("    [{}, [{}]]", prod.s, b.join(", "))
+            })
+            .collect();
+        writeln!(result, "  \"productions\": [\n{}\n  ],", productions.join(",\n")).unwrap();
+        writeln!(result, "  \"init_state\": {},", self.init_state).unwrap();
+        writeln!(result, "  \"term_count\": {},", self.term_count).unwrap();
+        writeln!(result, "  \"nonterm_count\": {},", self.nonterm_count).unwrap();
+        let action: Vec<_> = (0..self.state_count())
+            .map(|state| {
+                let moves: Vec<_> = self.actions(state)
+                    .map(|(term, curr_move)| match curr_move {
+                        Action::Shift(to) => format!("[{}, \"s{}\"]", term, to),
+                        Action::Reduce(prod) => format!("[{}, \"r{}\"]", term, prod),
+                    })
+                    .collect();
+                format!("    [{}]", moves.join(", "))
+            })
+            .collect();
+        writeln!(result, "  \"action\": [\n{}\n  ],", action.join(",\n")).unwrap();
+        let goto: Vec<_> = (0..self.state_count())
+            .map(|state| {
+                let gotos: Vec<_> = (0..self.nonterm_count)
+                    .filter_map(|nonterm| self.goto(state, nonterm).map(|to| format!("[{}, {}]", nonterm, to)))
+                    .collect();
+                format!("    [{}]", gotos.join(", "))
+            })
+            .collect();
+        writeln!(result, "  \"goto\": [\n{}\n  ]", goto.join(",\n")).unwrap();
+        result.push_str("}\n");
+        result
+    }
+
+    /// Reads back what `to_json` wrote
+    pub fn from_json(text: &str) -> Result<(ParseTable, SymbolNames), LoadError> {
+        let mut parser = JsonParser { text: text.as_bytes(), pos: 0 };
+        let root = parser.value()?;
+        parser.skip_space();
+        if parser.pos != parser.text.len() {
+            return Err(parser.error("text after the end"));
+        }
+
+        if root.field("format")?.string()? != JSON_FORMAT {
+            return load_error("not a file of parse tables");
+        }
+        check_version(root.field("version")?.number()?)?;
+        let strings = |name| -> Result<Vec<String>, LoadError> {
+            root.field(name)?.array()?.iter().map(|text| text.string().map(str::to_string)).collect()
+        };
+        let names = SymbolNames { terms: strings("terms")?, nonterms: strings("nonterms")? };
+
+        let mut productions = Vec::new();
+        for prod in root.field("productions")?.array()? {
+            let (s, b) = prod.pair()?;
+            let b = b.array()?
+                .iter()
+                .map(|sym| {
+                    let sym = sym.string()?;
+                    match tagged(sym) {
+                        Some(('t', term)) => Ok(Symbol::Term(term)),
+                        Some(('n', nonterm)) => Ok(Symbol::Nonterm(nonterm)),
+                        _ => load_error(format!("bad symbol {:?}", sym)),
+                    }
+                })
+                .collect::<Result<_, _>>()?;
+            productions.push(Production { s: s.number()?, b });
+        }
+
+        let term_count = root.field("term_count")?.number()?;
+        let nonterm_count = root.field("nonterm_count")?.number()?;
+        let action_rows = root.field("action")?.array()?;
+        let goto_rows = root.field("goto")?.array()?;
+        if goto_rows.len() != action_rows.len() {
+            return load_error("the action and goto tables have different numbers of states");
+        }
+        check_counts(term_count, nonterm_count, &names)?;
+        let mut table = ParseTable {
+            productions,
+            init_state: root.field("init_state")?.number()?,
+            term_count,
+            nonterm_count,
+            action: vec![None; action_rows.len() * term_count],
+            goto: vec![None; goto_rows.len() * nonterm_count],
+        };
+        for (state, row) in action_rows.iter().enumerate() {
+            for entry in row.array()? {
+                let (term, curr_move) = entry.pair()?;
+                let curr_move = curr_move.string()?;
+                let curr_move = match tagged(curr_move) {
+                    Some(('s', to)) => Action::Shift(to),
+                    Some(('r', prod)) => Action::Reduce(prod),
+                    _ => return load_error(format!("bad move {:?}", curr_move)),
+                };
+                table.set_action(state, term.number()?, curr_move)?;
+            }
+        }
+        for (state, row) in goto_rows.iter().enumerate() {
+            for entry in row.array()? {
+                let (nonterm, to) = entry.pair()?;
+                table.set_goto(state, nonterm.number()?, to.number()?)?;
+            }
+        }
+        table.check()?;
+        Ok((table, names))
+    }
+
+    /// Writes the tables and the names of the symbols in a compact binary
+    /// format: numbers are little endian `u32`, and only the moves there are
+    /// get written.
+    pub fn to_binary(&self, names: &SymbolNames) -> Vec<u8> {
+        let mut writer = BinaryWriter::default();
+        writer.bytes.extend_from_slice(BINARY_MAGIC);
+        writer.number(TABLES_VERSION);
+        for names in &[&names.terms, &names.nonterms] {
+            writer.number(names.len());
+            for name in names.iter() {
+                writer.number(name.len());
+                writer.bytes.extend_from_slice(name.as_bytes());
+            }
+        }
+        writer.number(self.productions.len());
+        for prod in &self.productions {
+            writer.number(prod.s);
+            writer.number(prod.b.len());
+            for &sym in &prod.b {
+                match sym {
+                    Symbol::Term(term) => writer.tagged(0, term),
+                    Symbol::Nonterm(nonterm) => writer.tagged(1, nonterm),
+                }
+            }
+        }
+        writer.number(self.init_state);
+        writer.number(self.term_count);
+        writer.number(self.nonterm_count);
+        writer.number(self.state_count());
+        for state in 0..self.state_count() {
+            let moves: Vec<_> = self.actions(state).collect();
+            writer.number(moves.len());
+            for (term, curr_move) in moves {
+                writer.number(term);
+                match curr_move {
+                    Action::Shift(to) => writer.tagged(0, to),
+                    Action::Reduce(prod) => writer.tagged(1, prod),
+                }
+            }
+            let gotos: Vec<_> = (0..self.nonterm_count)
+                .filter_map(|nonterm| self.goto(state, nonterm).map(|to| (nonterm, to)))
+                .collect();
+            writer.number(gotos.len());
+            for (nonterm, to) in gotos {
+                writer.number(nonterm);
+                writer.number(to);
+            }
+        }
+        writer.bytes
+    }
+
+    /// Reads back what `to_binary` wrote
+    pub fn from_binary(bytes: &[u8]) -> Result<(ParseTable, SymbolNames), LoadError> {
+        if !bytes.starts_with(BINARY_MAGIC) {
+            return load_error("not a file of parse tables");
+        }
+        let mut reader = BinaryReader { bytes, pos: BINARY_MAGIC.len() };
+        check_version(reader.number()?)?;
+        let mut names = SymbolNames { terms: Vec::new(), nonterms: Vec::new() };
+        for names in &mut [&mut names.terms, &mut names.nonterms] {
+            for _ in 0..reader.number()? {
+                let len = reader.number()?;
+                let name = reader.take(len)?;
+                match String::from_utf8(name.to_vec()) {
+                    Ok(name) => names.push(name),
+                    Err(..) => return load_error("a symbol name is not UTF-8"),
+                }
+            }
+        }
+        let mut productions = Vec::new();
+        for _ in 0..reader.number()? {
+            let s = reader.number()?;
+            let b = (0..reader.number()?)
+                .map(|_| match reader.tagged()? {
+                    (0, term) => Ok(Symbol::Term(term)),
+                    (1, nonterm) => Ok(Symbol::Nonterm(nonterm)),
+                    _ => load_error("bad symbol"),
+                })
+                .collect::<Result<_, _>>()?;
+            productions.push(Production { s, b });
+        }
+        let init_state = reader.number()?;
+        let term_count = reader.number()?;
+        let nonterm_count = reader.number()?;
+        let state_count = reader.number()?;
+        check_counts(term_count, nonterm_count, &names)?;
+        // Every state takes at least the two counts of its row
+        if state_count > (bytes.len() - reader.pos) / 8 {
+            return load_error("truncated file");
+        }
+        if state_count.checked_mul(term_count.max(nonterm_count)).is_none() {
+            return load_error("the tables are too large");
+        }
+        let mut table = ParseTable {
+            productions,
+            init_state,
+            term_count,
+            nonterm_count,
+            action: Vec::new(),
+            goto: Vec::new(),
+        };
+        for state in 0..state_count {
+            // Rows are only made once read, so that a broken file cannot make
+            // us allocate much more than it holds
+            table.action.resize((state + 1) * term_count, None);
+            table.goto.resize((state + 1) * nonterm_count, None);
+            for _ in 0..reader.number()? {
+                let term = reader.number()?;
+                let curr_move = match reader.tagged()? {
+                    (0, to) => Action::Shift(to),
+                    (1, prod) => Action::Reduce(prod),
+                    _ => return load_error("bad move"),
+                };
+                table.set_action(state, term, curr_move)?;
+            }
+            for _ in 0..reader.number()? {
+                let nonterm = reader.number()?;
+                table.set_goto(state, nonterm, reader.number()?)?;
+            }
+        }
+        if reader.pos != bytes.len() {
+            return load_error("bytes after the end");
+        }
+        table.check()?;
+        Ok((table, names))
+    }
+
+    fn set_action(&mut self, state: StateId, term: Term, curr_move: Action) -> Result<(), LoadError> {
+        if term >= self.term_count {
+            return load_error(format!("terminal {} out of range", term));
+        }
+        self.action[state * self.term_count + term] = Some(curr_move);
+        Ok(())
+    }
+
+    fn set_goto(&mut self, state: StateId, nonterm: Nonterm, to: StateId) -> Result<(), LoadError> {
+        if nonterm >= self.nonterm_count {
+            return load_error(format!("nonterminal {} out of range", nonterm));
+        }
+        self.goto[state * self.nonterm_count + nonterm] = Some(to);
+        Ok(())
+    }
+
+    /// Makes sure parsing with the loaded tables cannot go out of bounds,
+    /// nor reduce without a goto to follow
+    fn check(&self) -> Result<(), LoadError> {
+        let state_count = self.state_count();
+        if self.productions.is_empty() {
+            return load_error("there are no productions");
+        }
+        if self.init_state >= state_count {
+            return load_error("the initial state is out of range");
+        }
+        for prod in &self.productions {
+            let in_range = |&sym: &Symbol| match sym {
+                Symbol::Term(term) => term < self.term_count,
+                Symbol::Nonterm(nonterm) => nonterm < self.nonterm_count,
+            };
+            if prod.s >= self.nonterm_count || !prod.b.iter().all(in_range) {
+                return load_error("a production uses an unknown symbol");
+            }
+        }
+        for state in 0..state_count {
+            for (_, curr_move) in self.actions(state) {
+                let in_range = match curr_move {
+                    Action::Shift(to) => to < state_count,
+                    Action::Reduce(prod) => prod < self.productions.len(),
+                };
+                if !in_range {
+                    return load_error(format!("state {} has a move out of range", state));
+                }
+            }
+            if self.goto[state * self.nonterm_count..(state + 1) * self.nonterm_count]
+                .iter()
+                .any(|to| to.is_some_and(|to| to >= state_count))
+            {
+                return load_error(format!("state {} has a goto out of range", state));
+            }
+        }
+        if self.action(self.init_state, END_TERM).is_some_and(|curr_move| matches!(curr_move, Action::Shift(..))) {
+            return load_error("the initial state accepts");
+        }
+        self.check_reductions()
+    }
+
+    /// Walks back from every reduction over the states that can be below it
+    /// on the stack: none of them may be the bottom of the stack before the
+    /// whole right hand side is popped, and all must have a goto on the left
+    /// hand side
+    fn check_reductions(&self) -> Result<(), LoadError> {
+        let state_count = self.state_count();
+        // The states each state is entered from, and on which symbol
+        let mut sources: Vec<Vec<(StateId, Symbol)>> = vec![Vec::new(); state_count];
+        for state in 0..state_count {
+            for (term, curr_move) in self.actions(state) {
+                match curr_move {
+                    // Shifting $ accepts instead of entering the state
+                    Action::Shift(..) if term == END_TERM => {}
+                    Action::Shift(to) => sources[to].push((state, Symbol::Term(term))),
+                    Action::Reduce(..) => {}
+                }
+            }
+            for nonterm in 0..self.nonterm_count {
+                if let Some(to) = self.goto(state, nonterm) {
+                    sources[to].push((state, Symbol::Nonterm(nonterm)));
+                }
+            }
+        }
+        for state in 0..state_count {
+            for (_, curr_move) in self.actions(state) {
+                let prod = match curr_move {
+                    Action::Shift(..) => continue,
+                    Action::Reduce(prod) => &self.productions[prod],
+                };
+                let mut below: BTreeSet<StateId> = BTreeSet::new();
+                below.insert(state);
+                for sym in prod.b.iter().rev() {
+                    if below.contains(&self.init_state) {
+                        return load_error(format!("state {} reduces more symbols than the stack holds", state));
+                    }
+                    below = below
+                        .iter()
+                        .flat_map(|&to| sources[to].iter())
+                        .filter(|(_, on)| on == sym)
+                        .map(|&(from, _)| from)
+                        .collect();
+                }
+                if below.iter().any(|&from| self.goto(from, prod.s).is_none()) {
+                    return load_error(format!("state {} reduces to a state with no goto", state));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the sizes of the tables before allocating them
+fn check_counts(term_count: usize, nonterm_count: usize, names: &SymbolNames) -> Result<(), LoadError> {
+    if term_count <= ERROR_TERM || term_count > names.terms.len() {
+        return load_error("the terminals do not match the action table");
+    }
+    if nonterm_count == 0 || nonterm_count > names.nonterms.len() {
+        return load_error("the nonterminals do not match the goto table");
+    }
+    Ok(())
+}
+
+fn check_version(version: usize) -> Result<(), LoadError> {
+    if version != TABLES_VERSION {
+        return load_error(format!("version {} found, {} expected", version, TABLES_VERSION));
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct BinaryWriter {
+    bytes: Vec<u8>,
+}
+
+impl BinaryWriter {
+    fn number(&mut self, number: usize) {
+        self.bytes.extend_from_slice(&(number as u32).to_le_bytes());
+    }
+
+    fn tagged(&mut self, tag: u8, number: usize) {
+        self.bytes.push(tag);
+        self.number(number);
+    }
+}
+
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BinaryReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], LoadError> {
+        match self.bytes.get(self.pos..self.pos.saturating_add(len)) {
+            Some(taken) => {
+                self.pos += len;
+                Ok(taken)
+            }
+            None => load_error("truncated file"),
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, LoadError> {
+        let mut number = [0; 4];
+        number.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(number) as usize)
+    }
+
+    fn tagged(&mut self) -> Result<(u8, usize), LoadError> {
+        let tag = self.take(1)?[0];
+        Ok((tag, self.number()?))
+    }
+}
+
+/// The JSON values `to_json` writes
+enum Json {
+    Number(usize),
+    String(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+impl Json {
+    fn field(&self, name: &str) -> Result<&Json, LoadError> {
+        match self {
+            Json::Object(fields) => match fields.get(name) {
+                Some(value) => Ok(value),
+                None => load_error(format!("missing field {:?}", name)),
+            },
+            _ => load_error("object expected"),
+        }
+    }
+
+    fn number(&self) -> Result<usize, LoadError> {
+        match self {
+            Json::Number(number) => Ok(*number),
+            _ => load_error("number expected"),
+        }
+    }
+
+    fn string(&self) -> Result<&str, LoadError> {
+        match self {
+            Json::String(text) => Ok(text),
+            _ => load_error("string expected"),
+        }
+    }
+
+    fn array(&self) -> Result<&[Json], LoadError> {
+        match self {
+            Json::Array(values) => Ok(values),
+            _ => load_error("array expected"),
+        }
+    }
+
+    fn pair(&self) -> Result<(&Json, &Json), LoadError> {
+        match self.array()? {
+            [first, second] => Ok((first, second)),
+            _ => load_error("pair expected"),
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn error(&self, message: &str) -> LoadError {
+        LoadError { message: format!("{} at offset {}", message, self.pos) }
+    }
+
+    fn skip_space(&mut self) {
+        while self.text.get(self.pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skips `c` and the space after it, if it comes next
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_space();
+        if self.text.get(self.pos) != Some(&c) {
+            return false;
+        }
+        self.pos += 1;
+        true
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), LoadError> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.error(&format!("{:?} expected", c as char))),
+        }
+    }
+
+    /// The items of an array or object, after its opening bracket
+    fn items<T, F>(&mut self, close: u8, mut item: F) -> Result<Vec<T>, LoadError>
+    where
+        F: FnMut(&mut JsonParser<'a>) -> Result<T, LoadError>,
+    {
+        let mut items = Vec::new();
+        if self.eat(close) {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.eat(close) {
+                return Ok(items);
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, LoadError> {
+        self.skip_space();
+        match self.text.get(self.pos) {
+            Some(b'[') => {
+                self.pos += 1;
+                self.items(b']', JsonParser::value).map(Json::Array)
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let fields = self.items(b'}', |parser| {
+                    parser.skip_space();
+                    let name = parser.string()?;
+                    parser.expect(b':')?;
+                    Ok((name, parser.value()?))
+                })?;
+                Ok(Json::Object(fields.into_iter().collect()))
+            }
+            Some(b'"') => self.string().map(Json::String),
+            Some(c) if c.is_ascii_digit() => {
+                let start = self.pos;
+                while self.text.get(self.pos).is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+                let digits = std::str::from_utf8(&self.text[start..self.pos]).unwrap();
+                digits.parse().map(Json::Number).map_err(|_| self.error("number too big"))
+            }
+            Some(..) => Err(self.error("unsupported value")),
+            None => Err(self.error("value expected")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, LoadError> {
+        if self.text.get(self.pos) != Some(&b'"') {
+            return Err(self.error("string expected"));
+        }
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let c = match self.text.get(self.pos) {
+                Some(&c) => c,
+                None => return Err(self.error("unclosed string")),
+            };
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let escape = self.text.get(self.pos).cloned();
+                    self.pos += 1;
+                    match escape {
+                        Some(b'n') => bytes.push(b'\n'),
+                        Some(b't') => bytes.push(b'\t'),
+                        Some(b'r') => bytes.push(b'\r'),
+                        Some(b'u') => {
+                            let code = self.text
+                                .get(self.pos..self.pos + 4)
+                                .and_then(|code| std::str::from_utf8(code).ok())
+                                .and_then(|code| u32::from_str_radix(code, 16).ok())
+                                .and_then(std::char::from_u32);
+                            match code {
+                                Some(c) => bytes.extend_from_slice(c.to_string().as_bytes()),
+                                None => return Err(self.error("bad escape")),
+                            }
+                            self.pos += 4;
+                        }
+                        Some(c @ b'"') | Some(c @ b'\\') | Some(c @ b'/') => bytes.push(c),
+                        _ => return Err(self.error("bad escape")),
+                    }
+                }
+                c => bytes.push(c),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("string is not UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::canonical_lr_state::CanonicalLRState;
+    use crate::grammar::reader::{read_grammar, tokenize};
+
+    fn built() -> (ParseTable, GrammarSpec) {
+        let spec = read_grammar(r#"
+            %left + -
+            %left *
+            S -> S E ";" | error ";" | ε
+            E -> E + E | E - E | E * E | "(" E ")" | x
+        "#).unwrap();
+        let parser: LRGrammar<CanonicalLRState> =
+            LRGrammar::build_with_precedence(1, spec.productions.clone(), &spec.precedence)
+                .expect("Precedence should settle every conflict");
+        (parser.table().clone(), spec)
+    }
+
+    #[test]
+    fn round_trip() {
+        let (table, spec) = built();
+        let mut names = spec.names.clone();
+        names.nonterms[0] = "say \"ñ\"\\\n\u{1}".to_string();
+        let json = table.to_json(&names);
+        assert!(json.contains(r#""nonterms": ["say \"ñ\"\\\n\u0001", "S", "E"]"#));
+        let (from_json, json_names) = ParseTable::from_json(&json).unwrap();
+        assert_eq!(from_json, table);
+        assert_eq!(json_names.terms, names.terms);
+        assert_eq!(json_names.nonterms, names.nonterms);
+
+        let binary = table.to_binary(&names);
+        assert!(binary.len() < json.len());
+        let (from_binary, binary_names) = ParseTable::from_binary(&binary).unwrap();
+        assert_eq!(from_binary, table);
+        assert_eq!(binary_names.nonterms, names.nonterms);
+
+        let input = tokenize("x + (x); ) ; x * x;", &spec.names).unwrap();
+        let (tree, errors) = from_binary.parse_tree_recovering(&input);
+        assert_eq!(tree, table.parse_tree_recovering(&input).0);
+        assert!(tree.is_some());
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn load_errors() {
+        let (table, spec) = built();
+        let message = |result: Result<(ParseTable, SymbolNames), LoadError>| result.unwrap_err().message;
+        let json = table.to_json(&spec.names);
+        assert_eq!(
+            message(ParseTable::from_json(&json.replace("\"version\": 1", "\"version\": 7"))),
+            "version 7 found, 1 expected",
+        );
+        assert_eq!(message(ParseTable::from_json("{\"format\": \"other\"}")), "not a file of parse tables");
+        assert_eq!(message(ParseTable::from_json("[1, 2")), "',' expected at offset 5");
+        let shift = json.find("\"s").unwrap();
+        let end = shift + 1 + json[shift + 1..].find('"').unwrap();
+        let broken = format!("{}\"s999{}", &json[..shift], &json[end..]);
+        assert!(message(ParseTable::from_json(&broken)).ends_with("has a move out of range"));
+        // Reducing S' -> S $ from the initial state pops it
+        let broken = format!("{}\"r0{}", &json[..shift], &json[end..]);
+        assert!(message(ParseTable::from_json(&broken)).ends_with("reduces more symbols than the stack holds"));
+
+        let binary = table.to_binary(&spec.names);
+        assert_eq!(message(ParseTable::from_binary(&binary[..binary.len() - 1])), "truncated file");
+        assert_eq!(message(ParseTable::from_binary(json.as_bytes())), "not a file of parse tables");
+        // The gotos of the initial state, needed after reducing S -> ε
+        let gotos = json.find("\"goto\"").unwrap();
+        let row = gotos + json[gotos..].find("[[").unwrap();
+        let end = row + json[row..].find("]]").unwrap();
+        let broken = format!("{}[{}", &json[..row], &json[end + 1..]);
+        assert!(message(ParseTable::from_json(&broken)).ends_with("reduces to a state with no goto"));
+
+        let mut newer = binary.clone();
+        newer[4] = 2;
+        assert_eq!(message(ParseTable::from_binary(&newer)), "version 2 found, 1 expected");
+        let counts: Vec<u8> = [table.init_state, table.term_count, table.nonterm_count, table.state_count()]
+            .iter()
+            .flat_map(|&count| (count as u32).to_le_bytes().to_vec())
+            .collect();
+        let at = binary.windows(counts.len()).position(|window| window == &counts[..]).unwrap() + 12;
+        let mut huge = binary.clone();
+        huge[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(message(ParseTable::from_binary(&huge)), "truncated file");
+    }
+
+    #[test]
+    fn broken_reductions() {
+        let (mut table, spec) = built();
+        // Such a table does not load, but parsing with it still must not panic
+        let input = tokenize("x;", &spec.names).unwrap();
+        let init = table.init_state;
+        table.action[init * table.term_count + input[0].term] = Some(Action::Reduce(0));
+        assert!(table.parse(&input).is_err());
+    }
+}
@@ -1,2 +1,4 @@
 pub mod canonical_lr_state;
 pub mod slr_state;
+pub mod lalr_state;
+pub mod lr0_state;
@@ -3,7 +3,8 @@ use std::cmp::{PartialOrd, Ord, PartialEq, Eq};
 use crate::grammar::types::*;
 use crate::lr_grammar::{types::*, helpers::*};
 
-use std::collections::{BTreeSet, BTreeMap, VecDeque};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord)]
 struct SuperProduction {
@@ -73,15 +74,15 @@ impl LRState for CanonicalLRState {
         let sprod = SuperProduction {
             prod: initial_prod,
             prod_pos: 0,
-            c: b'$',
+            c: END_TERM,
         };
         let state = CanonicalLRState { sprods: vec![sprod] };
         state.closure(info)
     }
 
-    fn action<G: GrammarInfo>(&self, info: &G) -> Option<Vec<(Term, Move<CanonicalLRState>)>> {
+    fn action<G: GrammarInfo>(&self, info: &G) -> Vec<(Term, Move<CanonicalLRState>)> {
         let all_symbols = info.symbols();
-        let mut moves: BTreeMap<Term, Move<_>> = BTreeMap::new();
+        let mut moves = Vec::new();
         let all_terms: Vec<Term> = all_symbols
             .iter()
             .filter_map(|&sym| match sym { Symbol::Term(t) => Some(t), _ => None, })
@@ -89,19 +90,45 @@ impl LRState for CanonicalLRState {
         for sym in all_terms {
             let new_state = self.go_to(Symbol::Term(sym), info);
             if !new_state.sprods.is_empty() {
-                moves.insert(sym, Move::Shift(new_state));
+                moves.push((sym, Move::Shift(new_state)));
             }
         }
         for sprod in &self.sprods {
             if sprod.prod_pos == sprod.prod.b.len() {
-                let prev_value = moves.insert(sprod.c, Move::Reduce(sprod.prod.clone()));
-                // There is a confilct for the move
-                if prev_value.is_some() {
-                    return None;
+                moves.push((sprod.c, Move::Reduce(sprod.prod.clone())));
+            }
+        }
+        moves
+    }
+
+    fn items(&self) -> Vec<Item> {
+        // Items with the same core are adjacent, as the state is sorted
+        let mut items: Vec<Item> = Vec::new();
+        for sprod in &self.sprods {
+            match items.last_mut() {
+                Some(item) if item.prod == sprod.prod && item.pos == sprod.prod_pos => {
+                    item.lookaheads.push(sprod.c);
                 }
+                _ => items.push(Item {
+                    prod: sprod.prod.clone(),
+                    pos: sprod.prod_pos,
+                    lookaheads: vec![sprod.c],
+                }),
             }
         }
-        Some(moves.into_iter().collect())
+        items
+    }
+}
+
+impl fmt::Display for CanonicalLRState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_items(&self.items(), f)
+    }
+}
+
+impl fmt::Debug for CanonicalLRState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CanonicalLRState").field(&self.items()).finish()
     }
 }
 
@@ -115,137 +142,139 @@ mod tests {
         // A -> a
         // B -> Cb
         // C -> c | #
+        let (a, b, c, v) = (2, 3, 4, 5);
         let productions = vec![
             Production { s: 0, b: vec![Symbol::Nonterm(1), Symbol::Nonterm(2)] },
-            Production { s: 1, b: vec![Symbol::Term(b'a')] },
-            Production { s: 2, b: vec![Symbol::Nonterm(3), Symbol::Term(b'b')] },
-            Production { s: 3, b: vec![Symbol::Term(b'c')] },
+            Production { s: 1, b: vec![Symbol::Term(a)] },
+            Production { s: 2, b: vec![Symbol::Nonterm(3), Symbol::Term(b)] },
+            Production { s: 3, b: vec![Symbol::Term(c)] },
             Production { s: 3, b: vec![]},
         ];
         let init_nonterm = 0;
         let grammar = Grammar::build(init_nonterm, productions.clone());
         let state = CanonicalLRState {
             sprods: vec![
-                SuperProduction { prod: productions[0].clone(), prod_pos: 1, c: b'v' },
+                SuperProduction { prod: productions[0].clone(), prod_pos: 1, c: v },
             ],
         };
         let closure = state.closure(&grammar);
         assert_eq!(&closure.sprods, &vec![
-            SuperProduction { prod: productions[0].clone(), prod_pos: 1, c: b'v' },
-            SuperProduction { prod: productions[2].clone(), prod_pos: 0, c: b'v' },
-            SuperProduction { prod: productions[4].clone(), prod_pos: 0, c: b'b' },
-            SuperProduction { prod: productions[3].clone(), prod_pos: 0, c: b'b' },
+            SuperProduction { prod: productions[0].clone(), prod_pos: 1, c: v },
+            SuperProduction { prod: productions[2].clone(), prod_pos: 0, c: v },
+            SuperProduction { prod: productions[4].clone(), prod_pos: 0, c: b },
+            SuperProduction { prod: productions[3].clone(), prod_pos: 0, c: b },
         ]);
 
         let state = CanonicalLRState {
             sprods: vec![
-                SuperProduction { prod: productions[0].clone(), prod_pos: 0, c: b'v'},
+                SuperProduction { prod: productions[0].clone(), prod_pos: 0, c: v},
             ],
         };
         let closure = state.closure(&grammar);
         assert_eq!(&closure.sprods, &vec![
-            SuperProduction { prod: productions[0].clone(), prod_pos: 0, c: b'v' },
-            SuperProduction { prod: productions[1].clone(), prod_pos: 0, c: b'b' },
-            SuperProduction { prod: productions[1].clone(), prod_pos: 0, c: b'c' },
+            SuperProduction { prod: productions[0].clone(), prod_pos: 0, c: v },
+            SuperProduction { prod: productions[1].clone(), prod_pos: 0, c: b },
+            SuperProduction { prod: productions[1].clone(), prod_pos: 0, c },
         ]);
     }
 
     use crate::lr_grammar::LRGrammar;
+    use crate::grammar::reader::{read_grammar, tokenize};
+
     #[test]
     fn parse_1() {
-        // The grammar:
-        // S -> AB
-        // A -> a
-        // B -> Cb
-        // C -> c | #
-        let productions = vec![
-            Production { s: 0, b: vec![Symbol::Nonterm(1), Symbol::Nonterm(2)] },
-            Production { s: 1, b: vec![Symbol::Term(b'a')] },
-            Production { s: 2, b: vec![Symbol::Nonterm(3), Symbol::Term(b'b')] },
-            Production { s: 3, b: vec![Symbol::Term(b'c')] },
-            Production { s: 3, b: vec![]},
-        ];
-        let init_nonterm = 0;
-        
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            S -> A B
+            A -> a
+            B -> C b
+            C -> c
+            C ->
+        ").unwrap();
+        let init_nonterm = productions[0].s;
         let parser: LRGrammar<CanonicalLRState> = LRGrammar::build(init_nonterm, productions)
             .expect("Should be an LR(1) grammar");
-        assert!(parser.parse(b"acb").is_some());
-        assert!(parser.parse(b"ab").is_some());
-        assert!(parser.parse(b"a").is_none());
-        assert!(parser.parse(b"b").is_none());
-        assert!(parser.parse(b"c").is_none());
-        assert!(parser.parse(b"ac").is_none());
+        let parse = |input| parser.parse(&tokenize(input, &names).unwrap());
+        assert!(parse("acb").is_ok());
+        assert!(parse("ab").is_ok());
+        assert!(parse("a").is_err());
+        assert!(parse("b").is_err());
+        assert!(parse("c").is_err());
+        assert!(parse("ac").is_err());
     }
 
     #[test]
     fn parse_2() {
-        // The grammar:
-        // S -> AB
-        // A -> a
-        // B -> CB | D
-        // C -> c
-        // D -> #
-        let productions = vec![
-            Production { s: 0, b: vec![Symbol::Nonterm(1), Symbol::Nonterm(2)] },
-            Production { s: 1, b: vec![Symbol::Term(b'a')] },
-            Production { s: 2, b: vec![Symbol::Nonterm(3), Symbol::Nonterm(2)] },
-            Production { s: 2, b: vec![Symbol::Nonterm(4)] },
-            Production { s: 3, b: vec![Symbol::Term(b'c')] },
-            Production { s: 4, b: vec![]},
-        ];
-        let init_nonterm = 0;
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            S -> A B
+            A -> a
+            B -> C B
+            B -> D
+            C -> c
+            D ->
+        ").unwrap();
+        let init_nonterm = productions[0].s;
         let parser: LRGrammar<CanonicalLRState> = LRGrammar::build(init_nonterm, productions)
             .expect("Should be an LR(1) grammar");
-        assert!(parser.parse(b"acccc").is_some());
-        assert!(parser.parse(b"a").is_some());
-        assert!(parser.parse(b"c").is_none());
+        let parse = |input| parser.parse(&tokenize(input, &names).unwrap());
+        assert!(parse("acccc").is_ok());
+        assert!(parse("a").is_ok());
+        assert!(parse("c").is_err());
     }
 
     #[test]
     fn parse_3() {
-        // The grammar:
-        // S -> Aa
-        // S -> bAc
-        // S -> dc
-        // S -> bda
-        // A -> d
-        let productions = vec![
-            Production { s: 0, b: vec![Symbol::Nonterm(1), Symbol::Term(b'a')] },
-            Production { s: 0, b: vec![Symbol::Term(b'b'), Symbol::Nonterm(1), Symbol::Term(b'c')] },
-            Production { s: 0, b: vec![Symbol::Term(b'd'), Symbol::Term(b'c')] },
-            Production { s: 0, b: vec![Symbol::Term(b'b'), Symbol::Term(b'd'), Symbol::Term(b'a')] },
-            Production { s: 1, b: vec![Symbol::Term(b'd')] }
-        ];
-        let init_nonterm = 0;
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            S -> A a
+            S -> b A c
+            S -> d c
+            S -> b d a
+            A -> d
+        ").unwrap();
+        let init_nonterm = productions[0].s;
         let parser: LRGrammar<CanonicalLRState> = LRGrammar::build(init_nonterm, productions)
             .expect("Should be an LR(1) grammar");
-        assert!(parser.parse(b"da").is_some());
-        assert!(parser.parse(b"aa").is_none());
-        assert!(parser.parse(b"bdc").is_some());
+        let parse = |input| parser.parse(&tokenize(input, &names).unwrap());
+        assert!(parse("da").is_ok());
+        assert!(parse("aa").is_err());
+        assert!(parse("bdc").is_ok());
     }
 
     #[test]
     fn parse4() {
-        // The grammar:
-        // E -> E + T
-        // E -> T
-        // T -> T * F
-        // T -> F
-        // F -> n
-        let productions = vec![
-            Production { s: 0, b: vec![Symbol::Nonterm(0), Symbol::Term(b'+'), Symbol::Nonterm(1)] },
-            Production { s: 0, b: vec![Symbol::Nonterm(1)] },
-            Production { s: 1, b: vec![Symbol::Nonterm(1), Symbol::Term(b'*'), Symbol::Nonterm(2)] },
-            Production { s: 1, b: vec![Symbol::Nonterm(2)] },
-            Production { s: 2, b: vec![Symbol::Term(b'n')] },
-        ];
-        let init_nonterm = 0;
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            E -> E + T
+            E -> T
+            T -> T * F
+            T -> F
+            F -> n
+        ").unwrap();
+        let init_nonterm = productions[0].s;
         let parser: LRGrammar<CanonicalLRState> = LRGrammar::build(init_nonterm, productions)
             .expect("Should be LR(1)");
-        assert!(parser.parse(b"n*n*n").is_some());
-        assert!(parser.parse(b"n+n+n").is_some());
-        assert!(parser.parse(b"n+n*n+n*n").is_some());
-        assert!(parser.parse(b"n+n*").is_none());
-        assert!(parser.parse(b"n+*n").is_none());
+        let parse = |input| parser.parse(&tokenize(input, &names).unwrap());
+        assert!(parse("n*n*n").is_ok());
+        assert!(parse("n+n+n").is_ok());
+        assert!(parse("n+n*n+n*n").is_ok());
+        assert!(parse("n+n*").is_err());
+        assert!(parse("n+*n").is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn multi_character_terminals() {
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            %token IDENT
+            Stmt -> if Cond then Stmt else Stmt
+            Stmt -> IDENT := IDENT
+            Cond -> IDENT == IDENT
+            Cond -> IDENT <= IDENT
+        ").unwrap();
+        let init_nonterm = productions[0].s;
+        let parser: LRGrammar<CanonicalLRState> = LRGrammar::build(init_nonterm, productions)
+            .expect("Should be LR(1)");
+        let parse = |input| parser.parse(&tokenize(input, &names).unwrap());
+        assert!(parse("IDENT := IDENT").is_ok());
+        assert!(parse("if IDENT <= IDENT then IDENT := IDENT else IDENT := IDENT").is_ok());
+        assert!(parse("if IDENT then IDENT := IDENT else IDENT := IDENT").is_err());
+        assert!(parse("IDENT == IDENT").is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use std::cmp::{PartialOrd, Ord, PartialEq, Eq, Ordering};
+use std::collections::{BTreeSet, BTreeMap, VecDeque};
+use std::fmt;
+
+use crate::grammar::types::*;
+use crate::lr_grammar::{types::*, helpers::*};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord)]
+struct SuperProduction {
+    prod: Production,
+    prod_pos: usize,
+    cs: BTreeSet<Term>,
+}
+
+/// A LALR(1) state: an LR(0) core whose items carry the lookaheads of every
+/// canonical LR(1) state with the same core.
+///
+/// States are compared by their core only, so a state reached through `go_to`
+/// (which only knows the lookaheads of its predecessor) is found under the
+/// merged state in the action table.
+#[derive(Clone)]
+pub struct LALRState {
+    sprods: Vec<SuperProduction>,
+}
+
+impl LALRState {
+    fn core(&self) -> impl Iterator<Item = (&Production, usize)> {
+        self.sprods.iter().map(|sprod| (&sprod.prod, sprod.prod_pos))
+    }
+
+    /// Adds the lookaheads of `other`, which must have the same core.
+    /// Returns whether anything changed.
+    fn merge(&mut self, other: &LALRState) -> bool {
+        let mut change = false;
+        for (sprod, other_sprod) in self.sprods.iter_mut().zip(&other.sprods) {
+            for &c in &other_sprod.cs {
+                change |= sprod.cs.insert(c);
+            }
+        }
+        change
+    }
+}
+
+impl PartialEq for LALRState {
+    fn eq(&self, other: &LALRState) -> bool {
+        self.core().eq(other.core())
+    }
+}
+
+impl Eq for LALRState {}
+
+impl PartialOrd for LALRState {
+    fn partial_cmp(&self, other: &LALRState) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LALRState {
+    fn cmp(&self, other: &LALRState) -> Ordering {
+        self.core().cmp(other.core())
+    }
+}
+
+impl LRState for LALRState {
+    fn closure<G: GrammarInfo>(&self, info: &G) -> LALRState {
+        let mut result_sprods: BTreeMap<(Production, usize), BTreeSet<Term>> = BTreeMap::new();
+        let mut q = VecDeque::new();
+        for sprod in &self.sprods {
+            let key = (sprod.prod.clone(), sprod.prod_pos);
+            result_sprods.entry(key.clone()).or_default().extend(&sprod.cs);
+            q.push_back(key);
+        }
+        while let Some((prod, prod_pos)) = q.pop_front() {
+            if prod_pos < prod.b.len() {
+                if let Symbol::Nonterm(t) = prod.b[prod_pos] {
+                    let (mut new_cs, nullable) = get_first(&prod.b[(prod_pos + 1)..], info);
+                    if nullable {
+                        new_cs.extend(&result_sprods[&(prod.clone(), prod_pos)]);
+                    }
+                    for new_prod in info.prods_of(t) {
+                        let key = (new_prod, 0);
+                        let cs = result_sprods.entry(key.clone()).or_default();
+                        let mut change = false;
+                        for &new_c in &new_cs {
+                            change |= cs.insert(new_c);
+                        }
+                        // Lookaheads grew, so they have to be passed on again
+                        if change {
+                            q.push_back(key);
+                        }
+                    }
+                }
+            }
+        }
+        LALRState {
+            sprods: result_sprods
+                .into_iter()
+                .map(|((prod, prod_pos), cs)| SuperProduction { prod, prod_pos, cs })
+                .collect(),
+        }
+    }
+
+    fn go_to<G: GrammarInfo>(&self, sym: Symbol, info: &G) -> LALRState {
+        let mut new_sprods = Vec::new();
+        for sprod in &self.sprods {
+            if sprod.prod_pos < sprod.prod.b.len() && sprod.prod.b[sprod.prod_pos] == sym {
+                new_sprods.push(SuperProduction {
+                    prod: sprod.prod.clone(),
+                    prod_pos: sprod.prod_pos + 1,
+                    cs: sprod.cs.clone(),
+                });
+            }
+        }
+        LALRState { sprods: new_sprods }.closure(info)
+    }
+
+    fn initial_state<G: GrammarInfo>(initial_prod: Production, info: &G) -> LALRState {
+        let sprod = SuperProduction {
+            prod: initial_prod,
+            prod_pos: 0,
+            cs: vec![END_TERM].into_iter().collect(),
+        };
+        let state = LALRState { sprods: vec![sprod] };
+        state.closure(info)
+    }
+
+    fn action<G: GrammarInfo>(&self, info: &G) -> Vec<(Term, Move<LALRState>)> {
+        let all_symbols = info.symbols();
+        let mut moves = Vec::new();
+        let all_terms: Vec<Term> = all_symbols
+            .iter()
+            .filter_map(|&sym| match sym { Symbol::Term(t) => Some(t), _ => None, })
+            .collect();
+        for sym in all_terms {
+            let new_state = self.go_to(Symbol::Term(sym), info);
+            if !new_state.sprods.is_empty() {
+                moves.push((sym, Move::Shift(new_state)));
+            }
+        }
+        for sprod in &self.sprods {
+            if sprod.prod_pos == sprod.prod.b.len() {
+                for &c in &sprod.cs {
+                    moves.push((c, Move::Reduce(sprod.prod.clone())));
+                }
+            }
+        }
+        moves
+    }
+
+    fn items(&self) -> Vec<Item> {
+        self.sprods
+            .iter()
+            .map(|sprod| Item {
+                prod: sprod.prod.clone(),
+                pos: sprod.prod_pos,
+                lookaheads: sprod.cs.iter().cloned().collect(),
+            })
+            .collect()
+    }
+
+    /// Builds the LR(0) automaton, merging the lookaheads of states with the
+    /// same core and propagating them again whenever a merge adds something.
+    fn all_states<G: GrammarInfo>(from: LALRState, info: &G) -> Vec<LALRState> {
+        let symbols = info.symbols();
+
+        let mut states = vec![from.clone()];
+        let mut index = BTreeMap::new();
+        let mut q = VecDeque::new();
+        index.insert(from, 0);
+        q.push_back(0);
+
+        while let Some(first) = q.pop_front() {
+            for &symbol in &symbols {
+                let new_state = states[first].go_to(symbol, info);
+                if new_state.sprods.is_empty() {
+                    continue;
+                }
+                match index.get(&new_state) {
+                    Some(&i) => {
+                        if states[i].merge(&new_state) {
+                            q.push_back(i);
+                        }
+                    }
+                    None => {
+                        index.insert(new_state.clone(), states.len());
+                        q.push_back(states.len());
+                        states.push(new_state);
+                    }
+                }
+            }
+        }
+        states
+    }
+}
+
+impl fmt::Display for LALRState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_items(&self.items(), f)
+    }
+}
+
+impl fmt::Debug for LALRState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("LALRState").field(&self.items()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::{canonical_lr_state::CanonicalLRState, slr_state::SLRState};
+    use crate::grammar::reader::{read_grammar, tokenize};
+
+    #[test]
+    fn lalr_not_slr_1() {
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            S -> L = R
+            S -> R
+            L -> * R
+            L -> i
+            R -> L
+        ").unwrap();
+        let init_nonterm = productions[0].s;
+        assert!(LRGrammar::<SLRState>::build(init_nonterm, productions.clone()).is_err());
+        let parser: LRGrammar<LALRState> = LRGrammar::build(init_nonterm, productions)
+            .expect("Should be LALR(1)");
+        let parse = |input| parser.parse(&tokenize(input, &names).unwrap());
+        assert!(parse("i=*i").is_ok());
+        assert!(parse("**i=i").is_ok());
+        assert!(parse("i").is_ok());
+        assert!(parse("i=").is_err());
+        assert!(parse("=i").is_err());
+        assert!(parse("i=i=i").is_err());
+    }
+
+    #[test]
+    fn lalr_not_slr_2() {
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            S -> A a
+            S -> b A c
+            S -> d c
+            S -> b d a
+            A -> d
+        ").unwrap();
+        let init_nonterm = productions[0].s;
+        assert!(LRGrammar::<SLRState>::build(init_nonterm, productions.clone()).is_err());
+        let parser: LRGrammar<LALRState> = LRGrammar::build(init_nonterm, productions)
+            .expect("Should be LALR(1)");
+        let parse = |input| parser.parse(&tokenize(input, &names).unwrap());
+        assert!(parse("da").is_ok());
+        assert!(parse("bdc").is_ok());
+        assert!(parse("bda").is_ok());
+        assert!(parse("dc").is_ok());
+        assert!(parse("aa").is_err());
+        assert!(parse("bdd").is_err());
+    }
+
+    #[test]
+    fn lr1_not_lalr() {
+        let GrammarSpec { productions, .. } = read_grammar("
+            S -> a A d
+            S -> b B d
+            S -> a B e
+            S -> b A e
+            A -> c
+            B -> c
+        ").unwrap();
+        let init_nonterm = productions[0].s;
+        assert!(LRGrammar::<CanonicalLRState>::build(init_nonterm, productions.clone()).is_ok());
+        assert!(LRGrammar::<LALRState>::build(init_nonterm, productions).is_err());
+    }
+
+    #[test]
+    fn fewer_states() {
+        let GrammarSpec { productions, names, .. } = read_grammar(r#"
+            E -> E + T
+            E -> T
+            T -> T * F
+            T -> F
+            F -> "(" E ")"
+            F -> n
+        "#).unwrap();
+        let init_nonterm = productions[0].s;
+        let canonical: LRGrammar<CanonicalLRState> = LRGrammar::build(init_nonterm, productions.clone())
+            .expect("Should be LR(1)");
+        let lalr: LRGrammar<LALRState> = LRGrammar::build(init_nonterm, productions)
+            .expect("Should be LALR(1)");
+        assert!(lalr.get_states().len() < canonical.get_states().len());
+        let parse = |input| lalr.parse(&tokenize(input, &names).unwrap());
+        assert!(parse("(n+n)*n+n").is_ok());
+        assert!(parse("((n))").is_ok());
+        assert!(parse("(n+n").is_err());
+        assert!(parse("n+n)").is_err());
+    }
+}
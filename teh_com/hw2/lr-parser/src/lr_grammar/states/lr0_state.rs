@@ -0,0 +1,188 @@
+use std::cmp::{PartialOrd, Ord, PartialEq, Eq};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+
+use crate::lr_grammar::types::{LRState, GrammarInfo, Move, Item};
+use crate::lr_grammar::helpers::write_items;
+use crate::grammar::types::{Production, Symbol, Term};
+
+#[derive(Clone, PartialOrd, PartialEq, Ord, Eq)]
+struct SuperProduction {
+    prod: Production,
+    prod_pos: usize,
+}
+
+/// A state of the LR(0) automaton. Completed items reduce on every terminal,
+/// so any state holding a completed item next to another item that can move
+/// on a terminal or is itself completed is a conflict.
+#[derive(Clone, PartialOrd, PartialEq, Ord, Eq)]
+pub struct LR0State {
+    sprods: Vec<SuperProduction>,
+}
+
+impl LRState for LR0State {
+    fn closure<G: GrammarInfo>(&self, info: &G) -> LR0State {
+        let mut result_sprods = BTreeSet::new();
+        let mut q = VecDeque::new();
+        for sprod in &self.sprods {
+            result_sprods.insert(sprod.clone());
+            q.push_back(sprod.clone());
+        }
+        while !q.is_empty() {
+            let first = q.pop_front().unwrap();
+            if first.prod_pos < first.prod.b.len() {
+                let extend_sym = first.prod.b[first.prod_pos];
+                if let Symbol::Nonterm(t) = extend_sym {
+                    for new_prod in info.prods_of(t) {
+                        let new_sprod = SuperProduction {
+                            prod: new_prod.clone(),
+                            prod_pos: 0,
+                        };
+                        if result_sprods.insert(new_sprod.clone()) {
+                            q.push_back(new_sprod);
+                        }
+                    }
+                }
+            }
+        }
+        LR0State {
+            sprods: result_sprods.into_iter().collect(),
+        }
+    }
+
+    fn initial_state<G: GrammarInfo>(initial_prod: Production, info: &G) -> LR0State {
+        let sprod = SuperProduction {
+            prod: initial_prod,
+            prod_pos: 0,
+        };
+        let state = LR0State { sprods: vec![sprod] };
+        state.closure(info)
+    }
+
+    fn go_to<G: GrammarInfo>(&self, sym: Symbol, info: &G) -> LR0State {
+        let mut new_sprods = BTreeSet::new();
+        for sprod in &self.sprods {
+            if sprod.prod_pos < sprod.prod.b.len() && sprod.prod.b[sprod.prod_pos] == sym {
+                let new_sprod = SuperProduction {
+                    prod: sprod.prod.clone(),
+                    prod_pos: sprod.prod_pos + 1,
+                };
+                new_sprods.insert(new_sprod);
+            }
+        }
+        LR0State { sprods: new_sprods.into_iter().collect() }.closure(info)
+    }
+
+    fn action<G: GrammarInfo>(&self, info: &G) -> Vec<(Term, Move<LR0State>)> {
+        let all_symbols = info.symbols();
+        let mut moves = Vec::new();
+        let all_terms: Vec<Term> = all_symbols
+            .iter()
+            .filter_map(|&sym| match sym { Symbol::Term(t) => Some(t), _ => None, })
+            .collect();
+        for &sym in &all_terms {
+            let new_state = self.go_to(Symbol::Term(sym), info);
+            if !new_state.sprods.is_empty() {
+                moves.push((sym, Move::Shift(new_state)));
+            }
+        }
+        for sprod in &self.sprods {
+            if sprod.prod_pos == sprod.prod.b.len() {
+                for &c in &all_terms {
+                    moves.push((c, Move::Reduce(sprod.prod.clone())));
+                }
+            }
+        }
+        moves
+    }
+
+    fn items(&self) -> Vec<Item> {
+        self.sprods
+            .iter()
+            .map(|sprod| Item { prod: sprod.prod.clone(), pos: sprod.prod_pos, lookaheads: vec![] })
+            .collect()
+    }
+}
+
+impl fmt::Display for LR0State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_items(&self.items(), f)
+    }
+}
+
+impl fmt::Debug for LR0State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("LR0State").field(&self.items()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::states::slr_state::SLRState;
+    use crate::lr_grammar::types::ConflictKind;
+    use crate::grammar::reader::{read_grammar, tokenize};
+    use crate::grammar::types::GrammarSpec;
+
+    #[test]
+    fn parse1() {
+        let GrammarSpec { productions, names, .. } = read_grammar(r#"
+            S -> "(" L ")"
+            S -> x
+            L -> S
+            L -> L , S
+        "#).unwrap();
+        let init_nonterm = productions[0].s;
+        let parser: LRGrammar<LR0State> = LRGrammar::build(init_nonterm, productions)
+            .expect("Should be LR(0)");
+        let parse = |input| parser.parse(&tokenize(input, &names).unwrap());
+        assert!(parse("x").is_ok());
+        assert!(parse("(x)").is_ok());
+        assert!(parse("(x,(x,x),x)").is_ok());
+        assert!(parse("(x,)").is_err());
+        assert!(parse("(x").is_err());
+        assert!(parse("x,x").is_err());
+    }
+
+    #[test]
+    fn shift_reduce_conflict() {
+        let GrammarSpec { productions, .. } = read_grammar("
+            E -> E + T
+            E -> T
+            T -> T * F
+            T -> F
+            F -> n
+        ").unwrap();
+        let init_nonterm = productions[0].s;
+        assert!(LRGrammar::<SLRState>::build(init_nonterm, productions.clone()).is_ok());
+        assert!(LRGrammar::<LR0State>::build(init_nonterm, productions).is_err());
+    }
+
+    #[test]
+    fn reduce_reduce_conflict() {
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            S -> A b
+            S -> B c
+            A -> a
+            B -> a
+        ").unwrap();
+        let init_nonterm = productions[0].s;
+        assert!(LRGrammar::<SLRState>::build(init_nonterm, productions.clone()).is_ok());
+        let conflicts = LRGrammar::<LR0State>::build(init_nonterm, productions.clone())
+            .err()
+            .expect("Should not be LR(0)")
+            .conflicts;
+        assert!(!conflicts.is_empty());
+        for conflict in &conflicts {
+            assert_eq!(conflict.kind, ConflictKind::ReduceReduce);
+            assert!(conflict.shifts.is_empty());
+            assert_eq!(conflict.reductions, vec![productions[2].clone(), productions[3].clone()]);
+        }
+
+        let description = conflicts[0].describe(&names);
+        assert!(description.starts_with("reduce/reduce conflict"));
+        assert!(description.contains("A -> a ."));
+        assert!(description.contains("reduce: B -> a"));
+    }
+}
@@ -1,7 +1,9 @@
 use std::cmp::{PartialOrd, Ord, PartialEq, Eq};
-use std::collections::{BTreeSet, BTreeMap, VecDeque};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
 
-use crate::lr_grammar::types::{LRState, GrammarInfo, Move};
+use crate::lr_grammar::types::{LRState, GrammarInfo, Move, Item};
+use crate::lr_grammar::helpers::write_items;
 use crate::grammar::types::{Production, Symbol, Term};
 
 #[derive(Clone, PartialOrd, PartialEq, Ord, Eq)]
@@ -11,7 +13,7 @@ struct SuperProduction {
 }
 
 #[derive(Clone, PartialOrd, PartialEq, Ord, Eq)]
-struct SLRState {
+pub struct SLRState {
     sprods: Vec<SuperProduction>,
 }
 
@@ -68,9 +70,9 @@ impl LRState for SLRState {
         SLRState { sprods: new_sprods.into_iter().collect() }.closure(info)
     }
 
-    fn action<G: GrammarInfo>(&self, info: &G) -> Option<Vec<(Term, Move<SLRState>)>> {
+    fn action<G: GrammarInfo>(&self, info: &G) -> Vec<(Term, Move<SLRState>)> {
         let all_symbols = info.symbols();
-        let mut moves: BTreeMap<Term, Move<_>> = BTreeMap::new();
+        let mut moves = Vec::new();
         let all_terms: Vec<Term> = all_symbols
             .iter()
             .filter_map(|&sym| match sym { Symbol::Term(t) => Some(t), _ => None, })
@@ -78,21 +80,36 @@ impl LRState for SLRState {
         for sym in all_terms {
             let new_state = self.go_to(Symbol::Term(sym), info);
             if !new_state.sprods.is_empty() {
-                moves.insert(sym, Move::Shift(new_state));
+                moves.push((sym, Move::Shift(new_state)));
             }
         }
         for sprod in &self.sprods {
             if sprod.prod_pos == sprod.prod.b.len() {
                 for c in info.follow(sprod.prod.s) {
-                    let prev_value = moves.insert(c, Move::Reduce(sprod.prod.clone()));
-                    // There is a confilct for the move
-                    if prev_value.is_some() {
-                        return None;
-                    }
+                    moves.push((c, Move::Reduce(sprod.prod.clone())));
                 }
             }
         }
-        Some(moves.into_iter().collect())
+        moves
+    }
+
+    fn items(&self) -> Vec<Item> {
+        self.sprods
+            .iter()
+            .map(|sprod| Item { prod: sprod.prod.clone(), pos: sprod.prod_pos, lookaheads: vec![] })
+            .collect()
+    }
+}
+
+impl fmt::Display for SLRState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_items(&self.items(), f)
+    }
+}
+
+impl fmt::Debug for SLRState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("SLRState").field(&self.items()).finish()
     }
 }
 
@@ -100,49 +117,54 @@ impl LRState for SLRState {
 mod tests {
     use super::*;
     use crate::lr_grammar::LRGrammar;
+    use crate::lr_grammar::types::ConflictKind;
+    use crate::grammar::reader::{read_grammar, tokenize};
+    use crate::grammar::types::GrammarSpec;
 
     #[test]
     fn non_slr() {
-        // The grammar:
-        // S -> Aa
-        // S -> bAc
-        // S -> dc
-        // S -> bda
-        // A -> d
-        let productions = vec![
-            Production { s: 0, b: vec![Symbol::Nonterm(1), Symbol::Term(b'a')] },
-            Production { s: 0, b: vec![Symbol::Term(b'b'), Symbol::Nonterm(1), Symbol::Term(b'c')] },
-            Production { s: 0, b: vec![Symbol::Term(b'd'), Symbol::Term(b'c')] },
-            Production { s: 0, b: vec![Symbol::Term(b'b'), Symbol::Term(b'd'), Symbol::Term(b'a')] },
-            Production { s: 1, b: vec![Symbol::Term(b'd')] }
-        ];
-        let init_nonterm = 0;
-        let parser = LRGrammar::<SLRState>::build(init_nonterm, productions);
-        assert!(parser.is_none());
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            S -> A a
+            S -> b A c
+            S -> d c
+            S -> b d a
+            A -> d
+        ").unwrap();
+        let init_nonterm = productions[0].s;
+        let parser = LRGrammar::<SLRState>::build(init_nonterm, productions.clone());
+        let conflicts = parser.err().expect("Should not be SLR(1)").conflicts;
+
+        // Reading d, both S -> d.c and A -> d. are valid, and c is in Follow(A).
+        // The same happens for S -> bd.a and A -> d. on a
+        assert_eq!(conflicts.len(), 2);
+        let mut terms: Vec<_> = conflicts.iter().map(|conflict| names.terms[conflict.term].as_str()).collect();
+        terms.sort();
+        assert_eq!(terms, vec!["a", "c"]);
+        for conflict in &conflicts {
+            assert_eq!(conflict.kind, ConflictKind::ShiftReduce);
+            assert_eq!(conflict.reductions, vec![productions[4].clone()]);
+            assert_eq!(conflict.shifts.len(), 1);
+            assert_eq!(conflict.shifts[0].prod.b[conflict.shifts[0].pos], Symbol::Term(conflict.term));
+        }
     }
 
     #[test]
     fn parse1() {
-        // The grammar:
-        // E -> E + T
-        // E -> T
-        // T -> T * F
-        // T -> F
-        // F -> n
-        let productions = vec![
-            Production { s: 0, b: vec![Symbol::Nonterm(0), Symbol::Term(b'+'), Symbol::Nonterm(1)] },
-            Production { s: 0, b: vec![Symbol::Nonterm(1)] },
-            Production { s: 1, b: vec![Symbol::Nonterm(1), Symbol::Term(b'*'), Symbol::Nonterm(2)] },
-            Production { s: 1, b: vec![Symbol::Nonterm(2)] },
-            Production { s: 2, b: vec![Symbol::Term(b'n')] },
-        ];
-        let init_nonterm = 0;
+        let GrammarSpec { productions, names, .. } = read_grammar("
+            E -> E + T
+            E -> T
+            T -> T * F
+            T -> F
+            F -> n
+        ").unwrap();
+        let init_nonterm = productions[0].s;
         let parser: LRGrammar<SLRState> = LRGrammar::build(init_nonterm, productions)
             .expect("Should be SLR(1)");
-        assert!(parser.parse(b"n*n*n").is_some());
-        assert!(parser.parse(b"n+n+n").is_some());
-        assert!(parser.parse(b"n+n*n+n*n").is_some());
-        assert!(parser.parse(b"n+n*").is_none());
-        assert!(parser.parse(b"n+*n").is_none());
+        let parse = |input| parser.parse(&tokenize(input, &names).unwrap());
+        assert!(parse("n*n*n").is_ok());
+        assert!(parse("n+n+n").is_ok());
+        assert!(parse("n+n*n+n*n").is_ok());
+        assert!(parse("n+n*").is_err());
+        assert!(parse("n+*n").is_err());
     }
-}
\ No newline at end of file
+}
@@ -2,6 +2,7 @@ use std::cmp::{PartialOrd, PartialEq, Ord, Eq};
 use std::collections::{BTreeSet, VecDeque};
 
 use super::super::grammar::types::*;
+use super::counterexample::Counterexample;
 
 
 pub trait GrammarInfo {
@@ -17,7 +18,11 @@ pub trait LRState: Ord + Eq + Clone + Sized  {
     fn initial_state<G: GrammarInfo>(initial_prod: Production, info: &G) -> Self;
     fn go_to<G: GrammarInfo>(&self, sym: Symbol, info: &G) -> Self;
 
-    fn action<G: GrammarInfo>(&self, info: &G) -> Option<Vec<(Term, Move<Self>)>>;
+    /// Every move the state allows, possibly more than one per terminal.
+    /// Deciding whether they conflict is left to `LRGrammar::build`.
+    fn action<G: GrammarInfo>(&self, info: &G) -> Vec<(Term, Move<Self>)>;
+
+    fn items(&self) -> Vec<Item>;
 
     fn all_states<G: GrammarInfo>(from: Self, info: &G) -> Vec<Self> {
         let symbols = info.symbols();
@@ -45,3 +50,94 @@ pub enum Move<S: LRState> {
     Shift(S),
     Reduce(Production),
 }
+
+/// Index of a state in `LRGrammar::get_states`
+pub type StateId = usize;
+
+/// An entry of the ACTION table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Shift(StateId),
+    /// Index of the production in `ParseTable::productions`
+    Reduce(usize),
+}
+
+/// What the parser needs at run time: dense ACTION and GOTO tables, with a
+/// row per state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTable {
+    /// The productions of the grammar, after the made up `S' -> S $`
+    pub productions: Vec<Production>,
+    pub init_state: StateId,
+    pub term_count: usize,
+    pub nonterm_count: usize,
+    /// `term_count` entries per state
+    pub action: Vec<Option<Action>>,
+    /// `nonterm_count` entries per state
+    pub goto: Vec<Option<StateId>>,
+}
+
+/// Like `ParseTable`, but keeping every move of the conflicts, for GLR
+/// parsing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GLRTable {
+    pub productions: Vec<Production>,
+    pub init_state: StateId,
+    pub term_count: usize,
+    pub nonterm_count: usize,
+    /// `term_count` entries per state
+    pub action: Vec<Vec<Action>>,
+    /// `nonterm_count` entries per state
+    pub goto: Vec<Option<StateId>>,
+}
+
+/// A dotted production, with the lookaheads it is valid for (if the state
+/// type keeps track of them)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub prod: Production,
+    pub pos: usize,
+    pub lookaheads: Vec<Term>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    ShiftReduce,
+    ReduceReduce,
+}
+
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    /// Index of the state in `LRGrammar::get_states`, and of its row in the tables
+    pub state: usize,
+    pub items: Vec<Item>,
+    pub term: Term,
+    pub kind: ConflictKind,
+    /// Items of the state that would shift `term`
+    pub shifts: Vec<Item>,
+    /// Productions that could be reduced on `term`
+    pub reductions: Vec<Production>,
+    /// Filled in by `LRGrammar::build` once all the conflicts are known
+    pub counterexample: Option<Counterexample>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BuildError {
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Where the parser found no move to make
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: Position,
+    /// The terminal found there, `None` at the end of the input
+    pub unexpected: Option<Term>,
+    /// Terminals that have a move in the state the parser was in
+    pub expected: Vec<Term>,
+}
+
+/// A file of parse tables that could not be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadError {
+    pub message: String,
+}
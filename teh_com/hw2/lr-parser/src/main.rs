@@ -1,92 +1,222 @@
 pub mod lr_grammar;
 pub mod grammar;
+pub mod lexer;
+pub mod earley;
+pub mod cyk;
+pub mod ll_grammar;
 
-use std::collections::HashMap;
-use std::mem::MaybeUninit;
 use std::io::Read;
 
 use lr_grammar::LRGrammar;
+use ll_grammar::LLGrammar;
+use lr_grammar::types::{GLRTable, ParseTable};
 use lr_grammar::states::canonical_lr_state::CanonicalLRState;
-use grammar::types::{Nonterm, Term, Production, Symbol};
+use lr_grammar::dot::automaton_dot;
+use lr_grammar::report::{report, ReportFormat};
+use grammar::reader::read_grammar;
+use grammar::types::{Grammar, GrammarSpec, Lexeme};
+use lexer::Lexer;
 
-fn read_grammar(input: &str) -> (Vec<Production>, Vec<String>) {
-    let mut nonterms = HashMap::new();
-    nonterms.insert("S'".to_string(), 0);
-    let mut get_index = |s: String| -> usize {
-        if !nonterms.contains_key(&s) {
-            let index = nonterms.len();
-            nonterms.insert(s, index);
-            index
-        } else {
-            *nonterms.get(&s).unwrap()
-        }
+fn usage(program: &str) -> ! {
+    eprintln!("Usage: {} [--tables FILE] GRAMMAR < INPUT", program);
+    eprintln!("       {} --glr GRAMMAR < INPUT", program);
+    eprintln!("       {} --ll GRAMMAR < INPUT", program);
+    eprintln!("       {} --dot GRAMMAR > AUTOMATON.dot", program);
+    eprintln!("       {} --report text|markdown|html GRAMMAR", program);
+    eprintln!("       {} [--tables FILE] --rust GRAMMAR > PARSER.rs", program);
+    std::process::exit(2);
+}
+
+/// The tables cached in `path`, if they are newer than the grammar
+fn load_tables(path: &str, grammar_path: &str, spec: &GrammarSpec) -> Option<ParseTable> {
+    let modified = |path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    if modified(path)? < modified(grammar_path)? {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    let loaded = match path.ends_with(".json") {
+        true => ParseTable::from_json(&String::from_utf8_lossy(&bytes)),
+        false => ParseTable::from_binary(&bytes),
     };
-    let mut productions = Vec::new();
-    for line in input.split('\n') {
-        if line.trim().is_empty() {
-            continue;
+    match loaded {
+        Ok((table, names)) if names.terms == spec.names.terms && names.nonterms == spec.names.nonterms => Some(table),
+        Ok(..) => None,
+        Err(error) => {
+            eprintln!("{}: {}, building them again", path, error.describe());
+            None
         }
-        let parts: Vec<&str> = line
-            .trim()
-            .split(' ')
-            .filter(|p| p.trim().len() > 0)
-            .collect();
-        if parts.is_empty() {
-            continue;
+    }
+}
+
+/// The tokens of the standard input, or `None` after printing why it does
+/// not match
+fn read_tokens(lexer: &Lexer) -> Option<Vec<Lexeme>> {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).expect("Error reading");
+    match lexer.tokenize(&input) {
+        Ok(tokens) => Some(tokens),
+        Err(error) => {
+            println!("Doesn't match! {}", error.describe());
+            None
         }
-        if parts.len() < 3 || parts[1] != "->" {
-            panic!("Bad production");
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut emit_rust = false;
+    let mut glr = false;
+    let mut ll = false;
+    let mut dot = false;
+    let mut report_format = None;
+    let mut tables_path = None;
+    let mut grammar_path = None;
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--rust" => emit_rust = true,
+            "--glr" => glr = true,
+            "--ll" => ll = true,
+            "--dot" => dot = true,
+            "--report" => {
+                let name = rest.next().unwrap_or_else(|| usage(&args[0]));
+                report_format = Some(ReportFormat::from_name(name).unwrap_or_else(|| usage(&args[0])));
+            }
+            "--tables" => tables_path = Some(rest.next().unwrap_or_else(|| usage(&args[0]))),
+            _ if grammar_path.is_none() => grammar_path = Some(arg),
+            _ => usage(&args[0]),
+        }
+    }
+    let grammar_path = grammar_path.unwrap_or_else(|| usage(&args[0]));
+    let grammar_text = std::fs::read_to_string(grammar_path).expect("Error reading the grammar");
+    let spec = match read_grammar(&grammar_text) {
+        Ok(spec) => spec,
+        Err(error) => {
+            eprintln!("{}: {}", grammar_path, error.describe());
+            std::process::exit(1);
         }
-        if !parts[0].chars().next().unwrap().is_uppercase() {
-            panic!("Production with nonterm that starts with an non-uppercase character");
+    };
+    let names = &spec.names;
+    for lint in Grammar::build(spec.productions[0].s, spec.productions.clone()).lint() {
+        eprintln!("{}: warning: {}", grammar_path, lint.describe(names));
+    }
+    if dot {
+        let init_nonterm = spec.productions[0].s;
+        print!("{}", automaton_dot::<CanonicalLRState>(init_nonterm, spec.productions.clone(), &spec.precedence, names));
+        return;
+    }
+    if let Some(format) = report_format {
+        let init_nonterm = spec.productions[0].s;
+        print!("{}", report::<CanonicalLRState>(init_nonterm, spec.productions.clone(), &spec.precedence, names, format));
+        return;
+    }
+    let lexer = match Lexer::build(&spec) {
+        Ok(lexer) => lexer,
+        Err(error) => {
+            eprintln!("{}", error.describe());
+            std::process::exit(1);
         }
-        let s = get_index(parts[0].to_string());
-        let mut b = Vec::new();
-        for c in &parts[2..] {
-            let bytes: &[u8] = c.as_bytes();
-            if bytes.len() == 1 && !(bytes[0] as char).is_uppercase() {
-                b.push(Symbol::Term(bytes[0]));
-            } else {
-                b.push(Symbol::Nonterm(get_index(c.to_string())));
+    };
+    if glr {
+        let init_nonterm = spec.productions[0].s;
+        let table = GLRTable::build::<CanonicalLRState>(init_nonterm, spec.productions.clone(), &spec.precedence);
+        let tokens = match read_tokens(&lexer) {
+            Some(tokens) => tokens,
+            None => return,
+        };
+        match table.parse_forest(&tokens) {
+            Err(error) => println!("Doesn't match! {}", error.describe(names)),
+            Ok(forest) => {
+                match forest.count() {
+                    Some(count) => println!("Matches! {} parse(s)", count),
+                    None => println!("Matches! Infinitely many parses, showing those without cycles"),
+                }
+                for tree in forest.trees() {
+                    println!();
+                    print!("{}", tree.flatten(&spec.helpers).pretty(names));
+                }
             }
         }
-        productions.push(Production { s, b });
+        return;
     }
-    let mut ret_vec = Vec::with_capacity(nonterms.len());
-    for _ in 0..nonterms.len() {
-        ret_vec.push(MaybeUninit::<String>::uninit());
+    if ll {
+        let ll_grammar = match LLGrammar::build(spec.productions[0].s, spec.productions.clone()) {
+            Ok(ll_grammar) => ll_grammar,
+            Err(error) => {
+                eprintln!("Not an LL(1) grammar, found {} conflict(s):\n", error.conflicts.len());
+                eprint!("{}", error.describe(names));
+                std::process::exit(1);
+            }
+        };
+        let tokens = match read_tokens(&lexer) {
+            Some(tokens) => tokens,
+            None => return,
+        };
+        match ll_grammar.parse_tree(&tokens) {
+            Err(error) => println!("Doesn't match! {}", error.describe(names)),
+            Ok(tree) => {
+                let tree = tree.flatten(&spec.helpers);
+                println!("Matches!");
+                for step in tree.leftmost_derivation() {
+                    println!("{}", names.symbols(&step));
+                }
+                println!();
+                print!("{}", tree.pretty(names));
+            }
+        }
+        return;
     }
-    for (k, v) in nonterms.into_iter() {
-        unsafe { ret_vec[v].as_mut_ptr().write(k) };
+
+    let cached = tables_path.and_then(|path| load_tables(path, grammar_path, &spec));
+    let table = match cached {
+        Some(table) => table,
+        None => {
+            let init_nonterm = spec.productions[0].s;
+            let lr_grammar: LRGrammar<CanonicalLRState> = match LRGrammar::build_with_precedence(
+                init_nonterm,
+                spec.productions.clone(),
+                &spec.precedence,
+            ) {
+                Ok(lr_grammar) => lr_grammar,
+                Err(error) => {
+                    eprintln!("Not an LR(1) grammar, found {} conflict(s):\n", error.conflicts.len());
+                    eprint!("{}", error.describe(names));
+                    eprintln!("\nUse --glr to get every parse instead.");
+                    std::process::exit(1);
+                }
+            };
+            let table = lr_grammar.table().clone();
+            if let Some(path) = tables_path {
+                let bytes = match path.ends_with(".json") {
+                    true => table.to_json(names).into_bytes(),
+                    false => table.to_binary(names),
+                };
+                if let Err(error) = std::fs::write(path, bytes) {
+                    eprintln!("{}: {}", path, error);
+                }
+            }
+            table
+        }
+    };
+    if emit_rust {
+        print!("{}", table.generate_rust(names));
+        return;
     }
-    let ret_vec = ret_vec
-        .into_iter()
-        .map(|x| unsafe { x.assume_init() })
-        .collect();
-    (productions, ret_vec)
-}
 
-fn main() {
-    let mut input = String::new();
-    std::io::stdin().read_to_string(&mut input).expect("Error reading");
-    let (productions, nonterms) = read_grammar(&input);
-    let init_nonterm = productions[0].s;
-    let lr_grammar: LRGrammar<CanonicalLRState> = LRGrammar::build(init_nonterm, productions)
-        .expect("Not an LR(1) grammar");
-    let word = b"n+n*n+n*n";
-    match lr_grammar.parse(word) {
-        None => println!("Doesn't match!"),
-        Some(rightmost_derivation) => {
+    let tokens = match read_tokens(&lexer) {
+        Some(tokens) => tokens,
+        None => return,
+    };
+    match table.parse_tree(&tokens) {
+        Err(error) => println!("Doesn't match! {}", error.describe(names)),
+        Ok(tree) => {
+            let tree = tree.flatten(&spec.helpers);
             println!("Matches!");
-            for step in rightmost_derivation {
-                for s in step {
-                    match s {
-                        Symbol::Nonterm(t) => print!("{} ", nonterms[t]),
-                        Symbol::Term(t) => print!("{} ", t as char),
-                    }
-                }
-                println!();
+            for step in tree.rightmost_derivation() {
+                println!("{}", names.symbols(&step));
             }
+            println!();
+            print!("{}", tree.pretty(names));
         }
     }
 }